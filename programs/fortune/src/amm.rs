@@ -0,0 +1,361 @@
+use crate::error::FortuneError;
+use anchor_lang::prelude::*;
+use std::convert::TryFrom;
+
+// Pool supplies after a swap and the lamports paid in or out
+#[derive(Debug, PartialEq)]
+pub struct Swap {
+    pub ptoken_supply: u64,
+    pub lamport_supply: u64,
+    pub lamports: u64,
+}
+
+// Constant product k = ptoken_supply * lamport_supply, rounded in the pool's favor
+pub fn buy(ptoken_supply: u64, lamport_supply: u64, ptoken_amount: u64) -> Result<Swap> {
+    require!(ptoken_amount > 0, FortuneError::InvalidAmount);
+    // Pool always keeps at least one ptoken
+    require!(ptoken_amount < ptoken_supply, FortuneError::SoldOut);
+    let new_ptoken_supply = ptoken_supply - ptoken_amount;
+    let new_lamport_supply = div_ceil(
+        invariant(ptoken_supply, lamport_supply),
+        new_ptoken_supply as u128,
+    )?;
+    let new_lamport_supply = to_u64(new_lamport_supply)?;
+    Ok(Swap {
+        ptoken_supply: new_ptoken_supply,
+        lamport_supply: new_lamport_supply,
+        lamports: new_lamport_supply
+            .checked_sub(lamport_supply)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?,
+    })
+}
+
+// Inverse of buy, the pool keeps the rounding remainder
+pub fn sell(ptoken_supply: u64, lamport_supply: u64, ptoken_amount: u64) -> Result<Swap> {
+    require!(ptoken_amount > 0, FortuneError::InvalidAmount);
+    let new_ptoken_supply = ptoken_supply
+        .checked_add(ptoken_amount)
+        .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+    let new_lamport_supply = to_u64(div_ceil(
+        invariant(ptoken_supply, lamport_supply),
+        new_ptoken_supply as u128,
+    )?)?;
+    Ok(Swap {
+        ptoken_supply: new_ptoken_supply,
+        lamport_supply: new_lamport_supply,
+        lamports: lamport_supply
+            .checked_sub(new_lamport_supply)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?,
+    })
+}
+
+// Fee on a lamport amount, swap_fee / fee_scalar
+pub fn fee(lamports: u64, swap_fee: u64, fee_scalar: u64) -> Result<u64> {
+    let fee = (lamports as u128)
+        .checked_mul(swap_fee as u128)
+        .and_then(|n| n.checked_div(fee_scalar as u128))
+        .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+    to_u64(fee)
+}
+
+// Share of a lamport pot for ptoken_amount out of ptoken_total, rounded down
+pub fn pro_rata(ptoken_amount: u64, lamport_total: u64, ptoken_total: u64) -> Result<u64> {
+    require!(ptoken_amount > 0, FortuneError::InvalidAmount);
+    require!(
+        ptoken_amount <= ptoken_total,
+        FortuneError::InsufficientLiquidity
+    );
+    to_u64(ptoken_amount as u128 * lamport_total as u128 / ptoken_total as u128)
+}
+
+// Pool supplies after liquidity moves in or out at the current price, and the ptokens,
+// fees and shares minted or burnt with it
+#[derive(Debug, PartialEq)]
+pub struct Liquidity {
+    pub ptoken_supply: u64,
+    pub lamport_supply: u64,
+    pub ptokens: u64,
+    pub lamports: u64,
+    pub fees: u64,
+    pub shares: u64,
+}
+
+// Deposit lamports for shares of the lamport supply, ptokens are minted alongside so
+// the price holds. Shares also buy into the fees lp_shares have earned so far, that
+// part is paid into the fee vault. Everything rounds in the pool's favor.
+pub fn add_liquidity(
+    ptoken_supply: u64,
+    lamport_supply: u64,
+    total_shares: u64,
+    lp_shares: u64,
+    lp_fees: u64,
+    lamports: u64,
+) -> Result<Liquidity> {
+    require!(lamports > 0, FortuneError::InvalidAmount);
+    let (shares, fees) = if lp_shares == 0 {
+        (
+            lamports as u128 * total_shares as u128 / lamport_supply as u128,
+            0,
+        )
+    } else {
+        // lamports = shares * lamport_supply / total_shares + shares * lp_fees / lp_shares
+        let shares = (lamports as u128 * total_shares as u128)
+            .checked_mul(lp_shares as u128)
+            .and_then(|n| {
+                n.checked_div(
+                    lamport_supply as u128 * lp_shares as u128
+                        + lp_fees as u128 * total_shares as u128,
+                )
+            })
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+        let fees = shares
+            .checked_mul(lp_fees as u128)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+        (shares, div_ceil(fees, lp_shares as u128)?)
+    };
+    let fees = to_u64(fees)?;
+    let curve_lamports = lamports
+        .checked_sub(fees)
+        .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+    // Rounding the fees up may leave the curve part short of the shares
+    let shares =
+        to_u64(shares.min(curve_lamports as u128 * total_shares as u128 / lamport_supply as u128))?;
+    require!(shares > 0, FortuneError::InvalidAmount);
+    let ptokens = to_u64(curve_lamports as u128 * ptoken_supply as u128 / lamport_supply as u128)?;
+    Ok(Liquidity {
+        ptoken_supply: ptoken_supply
+            .checked_add(ptokens)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?,
+        lamport_supply: lamport_supply
+            .checked_add(curve_lamports)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?,
+        ptokens,
+        lamports: curve_lamports,
+        fees,
+        shares,
+    })
+}
+
+// Inverse of add_liquidity, shares are paid out of both supplies and the fees rounded
+// down
+pub fn remove_liquidity(
+    ptoken_supply: u64,
+    lamport_supply: u64,
+    total_shares: u64,
+    lp_shares: u64,
+    lp_fees: u64,
+    shares: u64,
+) -> Result<Liquidity> {
+    let lamports = pro_rata(shares, lamport_supply, total_shares)?;
+    let ptokens = pro_rata(shares, ptoken_supply, total_shares)?;
+    let fees = pro_rata(shares, lp_fees, lp_shares)?;
+    // Pool always keeps at least one ptoken
+    require!(ptokens < ptoken_supply, FortuneError::InsufficientLiquidity);
+    Ok(Liquidity {
+        ptoken_supply: ptoken_supply - ptokens,
+        lamport_supply: lamport_supply - lamports,
+        ptokens,
+        lamports,
+        fees,
+        shares,
+    })
+}
+
+fn invariant(ptoken_supply: u64, lamport_supply: u64) -> u128 {
+    // Cannot overflow, both factors fit in 64 bits
+    ptoken_supply as u128 * lamport_supply as u128
+}
+
+pub fn div_ceil(numerator: u128, denominator: u128) -> Result<u128> {
+    let quotient = numerator
+        .checked_div(denominator)
+        .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+    if quotient * denominator == numerator {
+        Ok(quotient)
+    } else {
+        Ok(quotient + 1)
+    }
+}
+
+pub fn to_u64(n: u128) -> Result<u64> {
+    u64::try_from(n).map_err(|_| error!(FortuneError::MathOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buy_follows_curve() {
+        let swap = buy(10, 10_000_000_000, 4).unwrap();
+        assert_eq!(swap.ptoken_supply, 6);
+        // 100_000_000_000 / 6 rounded up
+        assert_eq!(swap.lamport_supply, 16_666_666_667);
+        assert_eq!(swap.lamports, 6_666_666_667);
+    }
+
+    #[test]
+    fn buy_keeps_one_ptoken() {
+        assert!(buy(10, 1_000, 10).is_err());
+        assert!(buy(10, 1_000, 9).is_ok());
+    }
+
+    #[test]
+    fn buy_rejects_zero() {
+        assert!(buy(10, 1_000, 0).is_err());
+    }
+
+    #[test]
+    fn buy_large_pool_does_not_overflow() {
+        // k overflows u64 but not u128
+        let swap = buy(u64::MAX / 2, u64::MAX / 4, 1).unwrap();
+        assert!(swap.lamports >= 1);
+        assert!(swap.lamport_supply > u64::MAX / 4);
+    }
+
+    #[test]
+    fn buy_overflowing_lamport_supply_errors() {
+        assert!(buy(u64::MAX, u64::MAX, u64::MAX - 1).is_err());
+    }
+
+    #[test]
+    fn sell_after_buy_never_pays_more() {
+        for amount in 1..10 {
+            let bought = buy(10, 10_000_000_000, amount).unwrap();
+            let sold = sell(bought.ptoken_supply, bought.lamport_supply, amount).unwrap();
+            assert!(sold.lamports <= bought.lamports);
+            assert_eq!(sold.ptoken_supply, 10);
+        }
+    }
+
+    #[test]
+    fn sell_overflow_errors() {
+        assert!(sell(u64::MAX, 1, 1).is_err());
+        assert!(sell(10, 1_000, 0).is_err());
+    }
+
+    #[test]
+    fn pro_rata_splits_pot() {
+        assert_eq!(pro_rata(1, 1_000, 3).unwrap(), 333);
+        assert_eq!(pro_rata(3, 1_000, 3).unwrap(), 1_000);
+        assert_eq!(pro_rata(u64::MAX, u64::MAX, u64::MAX).unwrap(), u64::MAX);
+        assert!(pro_rata(4, 1_000, 3).is_err());
+        assert!(pro_rata(0, 1_000, 3).is_err());
+    }
+
+    #[test]
+    fn add_liquidity_keeps_price() {
+        let added = add_liquidity(10, 10_000_000_000, 10_000_000_000, 0, 0, 5_000_000_000).unwrap();
+        assert_eq!(added.ptokens, 5);
+        assert_eq!(added.shares, 5_000_000_000);
+        assert_eq!(added.ptoken_supply, 15);
+        assert_eq!(added.lamport_supply, 15_000_000_000);
+        // Shares are priced off the grown supply after a buy
+        let bought = buy(10, 10_000_000_000, 5).unwrap();
+        let added = add_liquidity(
+            5,
+            bought.lamport_supply,
+            10_000_000_000,
+            0,
+            0,
+            10_000_000_000,
+        )
+        .unwrap();
+        assert_eq!(added.shares, 5_000_000_000);
+        assert_eq!(added.ptokens, 2);
+    }
+
+    #[test]
+    fn add_liquidity_rejects_dust() {
+        assert!(add_liquidity(10, 1_000, 1_000, 0, 0, 0).is_err());
+        // Rounds to zero shares
+        assert!(add_liquidity(10, 1_000, 1, 0, 0, 999).is_err());
+    }
+
+    #[test]
+    fn remove_liquidity_never_pays_more() {
+        for lamports in [1, 7, 1_000, 3_333_333_333] {
+            let added = add_liquidity(10, 10_000_000_000, 10_000_000_000, 0, 0, lamports).unwrap();
+            let removed = remove_liquidity(
+                added.ptoken_supply,
+                added.lamport_supply,
+                10_000_000_000 + added.shares,
+                added.shares,
+                0,
+                added.shares,
+            )
+            .unwrap();
+            assert!(removed.lamports <= lamports);
+            assert!(removed.ptokens <= added.ptokens);
+        }
+    }
+
+    #[test]
+    fn remove_liquidity_keeps_one_ptoken() {
+        assert!(remove_liquidity(10, 1_000, 100, 100, 0, 100).is_err());
+        assert!(remove_liquidity(10, 1_000, 100, 100, 0, 101).is_err());
+        assert!(remove_liquidity(10, 1_000, 100, 100, 0, 0).is_err());
+        let removed = remove_liquidity(10, 1_000, 100, 100, 0, 50).unwrap();
+        assert_eq!(removed.lamports, 500);
+        assert_eq!(removed.ptoken_supply, 5);
+    }
+
+    #[test]
+    fn add_liquidity_buys_into_fees() {
+        // Half the shares are held by LPs who earned 1 SOL in fees
+        let added = add_liquidity(
+            10,
+            10_000_000_000,
+            20_000_000_000,
+            10_000_000_000,
+            1_000_000_000,
+            1_200_000_000,
+        )
+        .unwrap();
+        assert_eq!(added.shares, 2_000_000_000);
+        assert_eq!(added.fees, 200_000_000);
+        assert_eq!(added.lamports, 1_000_000_000);
+        assert_eq!(added.ptokens, 1);
+        // Pulling straight back out returns the deposit, the earlier fees stay put
+        let removed = remove_liquidity(
+            added.ptoken_supply,
+            added.lamport_supply,
+            20_000_000_000 + added.shares,
+            10_000_000_000 + added.shares,
+            1_000_000_000 + added.fees,
+            added.shares,
+        )
+        .unwrap();
+        assert!(removed.lamports + removed.fees <= 1_200_000_000);
+        assert_eq!(removed.fees, added.fees);
+        for lamports in [1_000, 7_777_777, 3_333_333_333] {
+            let added = add_liquidity(
+                10,
+                10_000_000_000,
+                20_000_000_000,
+                10_000_000_000,
+                1_000_000_000,
+                lamports,
+            )
+            .unwrap();
+            let removed = remove_liquidity(
+                added.ptoken_supply,
+                added.lamport_supply,
+                20_000_000_000 + added.shares,
+                10_000_000_000 + added.shares,
+                1_000_000_000 + added.fees,
+                added.shares,
+            )
+            .unwrap();
+            assert!(removed.lamports + removed.fees <= lamports);
+        }
+    }
+
+    #[test]
+    fn fee_scales() {
+        assert_eq!(fee(1_000, 25, 1000).unwrap(), 25);
+        assert_eq!(fee(u64::MAX, 1000, 1000).unwrap(), u64::MAX);
+        assert!(fee(u64::MAX, 2000, 1000).is_err());
+        assert!(fee(1_000, 25, 0).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+use crate::amm::{self, div_ceil, to_u64, Swap};
+use crate::error::FortuneError;
+use crate::Curve;
+use anchor_lang::prelude::*;
+
+// Fixed point scale of the exponential and logistic curves
+const WAD: u128 = 1_000_000_000_000_000_000;
+// e in WAD
+const E: u128 = 2_718_281_828_459_045_235;
+// How far the curves climb over the initial supply: linear to 1 + STEEPNESS times
+// the opening price, exponential e^STEEPNESS times, LMSR with a liquidity parameter
+// of 1 / STEEPNESS of the supply
+const STEEPNESS: u128 = 4;
+// Bound on the fixed point error of exp and softplus in WAD, costs are padded by it
+// in the pool's favor
+const EPSILON: u128 = 1_000_000;
+
+// Swap ptoken_amount out of the pool. Constant product prices off the supplies, the
+// other curves off the ptokens sold so far and open at the same price,
+// initial_lamport_supply / initial_ptoken_supply.
+pub fn buy(
+    curve: Curve,
+    initial_ptoken_supply: u64,
+    initial_lamport_supply: u64,
+    ptoken_supply: u64,
+    lamport_supply: u64,
+    ptoken_amount: u64,
+) -> Result<Swap> {
+    if curve == Curve::ConstantProduct {
+        return amm::buy(ptoken_supply, lamport_supply, ptoken_amount);
+    }
+    require!(ptoken_amount > 0, FortuneError::InvalidAmount);
+    // Pool always keeps at least one ptoken
+    require!(ptoken_amount < ptoken_supply, FortuneError::SoldOut);
+    let sold = initial_ptoken_supply
+        .checked_sub(ptoken_supply)
+        .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+    let lamports = cost(
+        curve,
+        initial_ptoken_supply,
+        initial_lamport_supply,
+        sold,
+        ptoken_amount,
+        true,
+    )?;
+    Ok(Swap {
+        ptoken_supply: ptoken_supply - ptoken_amount,
+        lamport_supply: lamport_supply
+            .checked_add(lamports)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?,
+        lamports,
+    })
+}
+
+// Inverse of buy, pays out the ptokens' part of the curve rounded down
+pub fn sell(
+    curve: Curve,
+    initial_ptoken_supply: u64,
+    initial_lamport_supply: u64,
+    ptoken_supply: u64,
+    lamport_supply: u64,
+    ptoken_amount: u64,
+) -> Result<Swap> {
+    if curve == Curve::ConstantProduct {
+        return amm::sell(ptoken_supply, lamport_supply, ptoken_amount);
+    }
+    require!(ptoken_amount > 0, FortuneError::InvalidAmount);
+    let sold = initial_ptoken_supply
+        .checked_sub(ptoken_supply)
+        .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+    require!(ptoken_amount <= sold, FortuneError::InsufficientLiquidity);
+    let lamports = cost(
+        curve,
+        initial_ptoken_supply,
+        initial_lamport_supply,
+        sold - ptoken_amount,
+        ptoken_amount,
+        false,
+    )?;
+    Ok(Swap {
+        ptoken_supply: ptoken_supply + ptoken_amount,
+        lamport_supply: lamport_supply
+            .checked_sub(lamports)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?,
+        lamports,
+    })
+}
+
+// Area under the price curve from sold to sold + ptoken_amount, rounded up for buys
+// and down for sells. The opening price is lamports / ptokens.
+fn cost(
+    curve: Curve,
+    ptokens: u64,
+    lamports: u64,
+    sold: u64,
+    ptoken_amount: u64,
+    round_up: bool,
+) -> Result<u64> {
+    let (n, l, s, a) = (
+        ptokens as u128,
+        lamports as u128,
+        sold as u128,
+        ptoken_amount as u128,
+    );
+    let overflow = || error!(FortuneError::MathOverflow);
+    let (numerator, denominator) = match curve {
+        Curve::ConstantProduct => unreachable!("constant product prices off the supplies"),
+        // l / n * (1 + STEEPNESS * t / n) integrated
+        Curve::Linear => {
+            let area = (2 * s + a)
+                .checked_mul(a)
+                .and_then(|x| x.checked_mul(STEEPNESS))
+                .and_then(|x| x.checked_add((a * n).checked_mul(2)?))
+                .ok_or_else(overflow)?;
+            (
+                l.checked_mul(area).ok_or_else(overflow)?,
+                n.checked_mul(n)
+                    .and_then(|x| x.checked_mul(2))
+                    .ok_or_else(overflow)?,
+            )
+        }
+        // l / n * e^(STEEPNESS * t / n) integrated
+        Curve::Exponential => {
+            let delta = bounded_delta(exp, n, s, a, round_up)?;
+            (l.checked_mul(delta).ok_or_else(overflow)?, STEEPNESS * WAD)
+        }
+        // Two outcome LMSR, 2 * l / n * sigmoid(STEEPNESS * t / n) integrated
+        Curve::Lmsr => {
+            let delta = bounded_delta(softplus, n, s, a, round_up)?;
+            (
+                l.checked_mul(delta)
+                    .and_then(|x| x.checked_mul(2))
+                    .ok_or_else(overflow)?,
+                STEEPNESS * WAD,
+            )
+        }
+    };
+    if round_up {
+        to_u64(div_ceil(numerator, denominator)?)
+    } else {
+        to_u64(numerator / denominator)
+    }
+}
+
+// f(x(s + a)) - f(x(s)) for an increasing f, padded by EPSILON so it is an upper
+// bound when rounding up and a lower bound otherwise
+fn bounded_delta(
+    f: fn(u128) -> Result<u128>,
+    n: u128,
+    s: u128,
+    a: u128,
+    round_up: bool,
+) -> Result<u128> {
+    // STEEPNESS * t / n in WAD
+    let x = |t: u128, up: bool| -> Result<u128> {
+        let numerator = STEEPNESS
+            .checked_mul(t)
+            .and_then(|x| x.checked_mul(WAD))
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?;
+        if up {
+            div_ceil(numerator, n)
+        } else {
+            Ok(numerator / n)
+        }
+    };
+    let high = f(x(s + a, round_up)?)?;
+    let low = f(x(s, !round_up)?)?;
+    if round_up {
+        Ok((high + 2 * EPSILON).saturating_sub(low))
+    } else {
+        Ok(high.saturating_sub(low + 2 * EPSILON))
+    }
+}
+
+// e^x in WAD, e^floor(x) times a Taylor series for the fraction
+fn exp(x: u128) -> Result<u128> {
+    let fraction = x % WAD;
+    let mut term = WAD;
+    let mut sum = WAD;
+    let mut k = 1;
+    while term > 0 {
+        term = term * fraction / WAD / k;
+        sum += term;
+        k += 1;
+    }
+    for _ in 0..x / WAD {
+        sum = sum
+            .checked_mul(E)
+            .ok_or_else(|| error!(FortuneError::MathOverflow))?
+            / WAD;
+    }
+    Ok(sum)
+}
+
+// ln(1 + e^x) in WAD, as x + ln(1 + e^-x) so the series converges
+fn softplus(x: u128) -> Result<u128> {
+    let u = WAD * WAD / exp(x)?;
+    // ln(1 + u) = 2 atanh(u / (2 + u)), u / (2 + u) is at most 1 / 3
+    let z = u * WAD / (2 * WAD + u);
+    let z2 = z * z / WAD;
+    let mut term = z;
+    let mut sum = 0;
+    let mut k = 1;
+    while term > 0 {
+        sum += term / k;
+        term = term * z2 / WAD;
+        k += 2;
+    }
+    Ok(x + 2 * sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [Curve; 4] = [
+        Curve::ConstantProduct,
+        Curve::Linear,
+        Curve::Exponential,
+        Curve::Lmsr,
+    ];
+
+    // xorshift, deterministic inputs for the property tests
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, max: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % max
+        }
+
+        fn between(&mut self, min: u64, max: u64) -> u64 {
+            min + self.below(max - min + 1)
+        }
+    }
+
+    fn first_ptoken(curve: Curve) -> u64 {
+        buy(curve, 10, 10_000_000_000, 10, 10_000_000_000, 1)
+            .unwrap()
+            .lamports
+    }
+
+    #[test]
+    fn curves_price_first_ptoken() {
+        assert_eq!(first_ptoken(Curve::ConstantProduct), 1_111_111_112);
+        // Opens at 1 SOL and climbs 0.4 SOL per ptoken
+        assert_eq!(first_ptoken(Curve::Linear), 1_200_000_000);
+        // 2.5 SOL * (e^0.4 - 1)
+        let lamports = first_ptoken(Curve::Exponential);
+        assert!((1_229_561_740..=1_229_561_750).contains(&lamports));
+        // 5 SOL * (ln(1 + e^0.4) - ln 2)
+        let lamports = first_ptoken(Curve::Lmsr);
+        assert!((1_099_340_355..=1_099_340_365).contains(&lamports));
+    }
+
+    #[test]
+    fn fixed_point_matches_float() {
+        for x in [0, 1, WAD / 3, WAD, 5 * WAD / 2, 4 * WAD] {
+            let float = x as f64 / WAD as f64;
+            let exp = exp(x).unwrap() as f64 / WAD as f64;
+            assert!((exp - float.exp()).abs() < 1e-12);
+            let softplus = softplus(x).unwrap() as f64 / WAD as f64;
+            assert!((softplus - float.exp().ln_1p()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn curves_keep_one_ptoken() {
+        for curve in CURVES {
+            assert!(buy(curve, 10, 1_000, 10, 1_000, 10).is_err());
+            assert!(buy(curve, 10, 1_000, 10, 1_000, 9).is_ok());
+            assert!(buy(curve, 10, 1_000, 10, 1_000, 0).is_err());
+        }
+    }
+
+    #[test]
+    fn sell_only_what_was_sold() {
+        for curve in [Curve::Linear, Curve::Exponential, Curve::Lmsr] {
+            let bought = buy(curve, 10, 1_000_000, 10, 1_000_000, 3).unwrap();
+            assert!(sell(curve, 10, 1_000_000, 7, bought.lamport_supply, 4).is_err());
+            let sold = sell(curve, 10, 1_000_000, 7, bought.lamport_supply, 3).unwrap();
+            assert_eq!(sold.ptoken_supply, 10);
+        }
+    }
+
+    #[test]
+    fn curves_are_monotonic() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..2_000 {
+            let n = rng.between(3, 1_000_000);
+            let l = rng.between(1_000_000, 10_000_000_000_000);
+            let sold = rng.below(n - 2);
+            let amount = rng.between(1, n - sold - 2);
+            for curve in CURVES {
+                let price = |sold: u64, amount: u64| {
+                    buy(curve, n, l, n - sold, l, amount).unwrap().lamports
+                };
+                // More ptokens cost more, and the same ptokens cost more later on
+                assert!(price(sold, amount + 1) > price(sold, amount));
+                assert!(price(sold + 1, amount) >= price(sold, amount));
+                // Curves other than constant product price off the ptokens sold only
+                if curve != Curve::ConstantProduct {
+                    let after = buy(curve, n, l, n - sold, l, amount).unwrap();
+                    let next = buy(curve, n, l, after.ptoken_supply, after.lamport_supply, 1);
+                    assert_eq!(next.unwrap().lamports, price(sold + amount, 1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn curves_stay_solvent() {
+        let mut rng = Rng(0xf047);
+        for _ in 0..300 {
+            let n = rng.between(2, 1_000_000_000);
+            let l = rng.between(1_000, 1_000_000_000_000_000);
+            for curve in CURVES {
+                let (mut ptoken_supply, mut lamport_supply) = (n, l);
+                // Lamports the pool actually holds, the initial supply is virtual
+                let mut vault = 0u64;
+                for _ in 0..20 {
+                    let sold = n - ptoken_supply;
+                    let swap = if sold > 0 && rng.below(3) == 0 {
+                        let amount = rng.between(1, sold);
+                        let swap =
+                            sell(curve, n, l, ptoken_supply, lamport_supply, amount).unwrap();
+                        vault = vault.checked_sub(swap.lamports).unwrap();
+                        swap
+                    } else if ptoken_supply > 1 {
+                        let amount = rng.between(1, (ptoken_supply - 1).min(1_000));
+                        let swap = buy(curve, n, l, ptoken_supply, lamport_supply, amount);
+                        let swap = match swap {
+                            Ok(swap) => swap,
+                            // Overflowing the lamport supply is rejected, not mispriced
+                            Err(_) => continue,
+                        };
+                        vault += swap.lamports;
+                        swap
+                    } else {
+                        continue;
+                    };
+                    ptoken_supply = swap.ptoken_supply;
+                    lamport_supply = swap.lamport_supply;
+                    assert_eq!(lamport_supply, l + vault);
+                }
+                // Everyone selling back is always covered by the vault
+                let sold = n - ptoken_supply;
+                if sold > 0 {
+                    let swap = sell(curve, n, l, ptoken_supply, lamport_supply, sold).unwrap();
+                    assert!(swap.lamports <= vault);
+                    assert_eq!(swap.ptoken_supply, n);
+                }
+            }
+        }
+    }
+}
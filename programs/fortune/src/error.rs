@@ -12,7 +12,7 @@ pub enum FortuneError {
     LockedListing,
     #[msg("Ask cannot be less than zero")]
     ZeroAsk,
-    #[msg("Maximum burn amount is 9")]
+    #[msg("Burn amount above the pool's limit")]
     BurnLimit,
     #[msg("Outstanding ptokens, cannot close pool")]
     OutstandingProb,
@@ -30,4 +30,86 @@ pub enum FortuneError {
     PtokenInitMax,
     #[msg("No more pTokens left to buy")]
     SoldOut,
+    #[msg("Not enough liquidity in pool")]
+    InsufficientLiquidity,
+    #[msg("Burn request slot hash not available yet")]
+    BurnNotReady,
+    #[msg("Missing or invalid oracle signature")]
+    InvalidOracleSignature,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Amount must be greater than zero")]
+    InvalidAmount,
+    #[msg("Slippage limit exceeded")]
+    SlippageExceeded,
+    #[msg("Fee scalar must be greater than zero")]
+    InvalidFeeScalar,
+    #[msg("Swap fee cannot exceed fee scalar")]
+    InvalidSwapFee,
+    #[msg("Init min must be below init max")]
+    InvalidInitRange,
+    #[msg("Not enough fees in vault")]
+    InsufficientFees,
+    #[msg("Deadline must be in the future")]
+    InvalidDeadline,
+    #[msg("Refund deadline not reached")]
+    RefundDeadline,
+    #[msg("Pool is not refunding")]
+    NotRefunding,
+    #[msg("Burn requests pending against pool")]
+    BurnPending,
+    #[msg("Pool is no longer open")]
+    PoolExpired,
+    #[msg("Claim deadline has passed")]
+    ClaimExpired,
+    #[msg("No pending burn request")]
+    NoBurnRequest,
+    #[msg("Burn request cannot be cancelled yet")]
+    BurnNotCancellable,
+    #[msg("Not available in this pool mode")]
+    WrongPoolMode,
+    #[msg("No raffle tickets sold")]
+    NoTickets,
+    #[msg("Raffle is still selling tickets")]
+    RaffleOpen,
+    #[msg("No pending raffle draw")]
+    NoDraw,
+    #[msg("Entry does not hold the winning ticket")]
+    WrongEntry,
+    #[msg("Pool holds the maximum number of prizes")]
+    PrizeLimit,
+    #[msg("Prize vault accounts missing or out of order")]
+    PrizeAccounts,
+    #[msg("Prize does not match its kind")]
+    InvalidPrize,
+    #[msg("Too many allowed collections")]
+    CollectionLimit,
+    #[msg("Metadata account does not match the mint")]
+    InvalidMetadata,
+    #[msg("Prize is not in an allowed verified collection")]
+    UnverifiedCollection,
+    #[msg("Default fee must be within its bounds")]
+    InvalidFeeBounds,
+    #[msg("Pool metadata field too long")]
+    MetadataTooLong,
+    #[msg("Pool fee outside protocol bounds")]
+    FeeOutOfBounds,
+    #[msg("Creator fee share cannot exceed 10000 bps")]
+    InvalidCreatorFee,
+    #[msg("Not available for this pool's curve")]
+    WrongCurve,
+    #[msg("Pool has not collected its reserve")]
+    ReserveNotMet,
+    #[msg("Wallet bought the most ptokens the pool allows")]
+    WalletLimit,
+    #[msg("Burn cooldown has not elapsed")]
+    BurnCooldown,
+    #[msg("Pools with a reserve do not take liquidity")]
+    ReserveLiquidity,
+    #[msg("Ptoken account is not the signer's")]
+    WrongPtokenAccount,
+    #[msg("Entry holds the most ticket ranges")]
+    EntryLimit,
+    #[msg("Raffle tickets cannot move before the draw")]
+    TicketsLocked,
 }
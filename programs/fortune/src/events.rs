@@ -0,0 +1,275 @@
+use crate::{BurnMode, Curve, OddsModel, PoolLimits, PoolMode, PrizeKind};
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct PoolCreated {
+    pub prob_pool: Pubkey,
+    pub authority: Pubkey,
+    pub nft_mint: Pubkey,
+    pub ptoken_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lamport_supply: u64,
+    pub ptoken_supply: u64,
+    pub open_until: i64,
+    pub claim_deadline: Option<i64>,
+    pub refund_deadline: i64,
+    pub odds_model: OddsModel,
+    pub curve: Curve,
+    pub reserve: u64,
+    pub limits: PoolLimits,
+    pub mode: PoolMode,
+    pub ticket_price: u64,
+    pub prize_kind: PrizeKind,
+    pub prize_amount: u64,
+    pub prize_weight: u64,
+}
+
+#[event]
+pub struct PoolMetadataUpdated {
+    pub prob_pool: Pubkey,
+    pub name: String,
+    pub uri: String,
+    pub category: String,
+    pub swap_fee: Option<u64>,
+    pub burn_cost: Option<u64>,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub prob_pool: Pubkey,
+    pub provider: Pubkey,
+    pub lamports: u64,
+    // Paid into the LP vault for a part of the fees already earned
+    pub fees: u64,
+    // Ptokens minted into the pool alongside
+    pub ptokens: u64,
+    pub shares: u64,
+    pub ptoken_supply: u64,
+    pub lamport_supply: u64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub prob_pool: Pubkey,
+    pub provider: Pubkey,
+    pub lamports: u64,
+    // Swap fees earned, paid on top of lamports
+    pub fees: u64,
+    // Ptokens burnt from the pool alongside
+    pub ptokens: u64,
+    pub shares: u64,
+    pub ptoken_supply: u64,
+    pub lamport_supply: u64,
+}
+
+#[event]
+pub struct LpRedeemed {
+    pub prob_pool: Pubkey,
+    pub provider: Pubkey,
+    pub shares: u64,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct PrizeAdded {
+    pub prob_pool: Pubkey,
+    pub mint: Pubkey,
+    pub kind: PrizeKind,
+    pub amount: u64,
+    pub weight: u64,
+}
+
+#[event]
+pub struct Bought {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub ptoken_amount: u64,
+    pub lamports: u64,
+    pub fee: u64,
+    // Part of fee paid to the pool creator
+    pub creator_fee: u64,
+    // Part of fee paid to liquidity providers
+    pub lp_fee: u64,
+    pub ptoken_supply: u64,
+    pub lamport_supply: u64,
+}
+
+#[event]
+pub struct Sold {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub ptoken_amount: u64,
+    pub lamports: u64,
+    pub fee: u64,
+    // Part of fee paid to the pool creator
+    pub creator_fee: u64,
+    // Part of fee paid to liquidity providers
+    pub lp_fee: u64,
+    pub ptoken_supply: u64,
+    pub lamport_supply: u64,
+}
+
+#[event]
+pub struct TicketsBought {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub entry: Pubkey,
+    pub first_ticket: u64,
+    pub tickets: u64,
+    pub lamports: u64,
+    pub fee: u64,
+    pub creator_fee: u64,
+}
+
+#[event]
+pub struct BurnRequested {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub ptoken_amount: u64,
+    pub burn_cost: u64,
+    pub creator_fee: u64,
+    pub target_slot: u64,
+    pub oracle: Pubkey,
+    pub seed: [u8; 32],
+}
+
+// draws is empty when the request expired
+#[event]
+pub struct BurnExecuted {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub ptoken_amount: u64,
+    // Ptokens the burn was drawn against under the pool's odds model
+    pub denominator: u64,
+    pub mode: BurnMode,
+    pub draws: Vec<u64>,
+    pub won: bool,
+    // Mint of the prize won, default pubkey otherwise
+    pub prize: Pubkey,
+    pub expired: bool,
+}
+
+#[event]
+pub struct BurnCancelled {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub ptoken_amount: u64,
+    pub refund: u64,
+}
+
+#[event]
+pub struct DrawRequested {
+    pub prob_pool: Pubkey,
+    pub tickets_sold: u64,
+    pub target_slot: u64,
+    pub oracle: Pubkey,
+    pub seed: [u8; 32],
+}
+
+// winner is the default pubkey when the draw expired
+#[event]
+pub struct RaffleDrawn {
+    pub prob_pool: Pubkey,
+    pub tickets_sold: u64,
+    pub ticket: u64,
+    pub winner: Pubkey,
+    pub expired: bool,
+}
+
+#[event]
+pub struct AssetClaimed {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub nft_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct Listed {
+    pub prob_pool: Pubkey,
+    pub listing: Pubkey,
+    pub seller: Pubkey,
+    pub ptoken_amount: u64,
+    pub ask: u64,
+}
+
+#[event]
+pub struct AskUpdated {
+    pub prob_pool: Pubkey,
+    pub listing: Pubkey,
+    pub ask: u64,
+}
+
+#[event]
+pub struct ListingCancelled {
+    pub prob_pool: Pubkey,
+    pub listing: Pubkey,
+    pub seller: Pubkey,
+    pub ptoken_amount: u64,
+}
+
+// Filled at the ask or by the seller accepting the best bid
+#[event]
+pub struct ListingFilled {
+    pub prob_pool: Pubkey,
+    pub listing: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub ptoken_amount: u64,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct BidPlaced {
+    pub prob_pool: Pubkey,
+    pub listing: Pubkey,
+    pub bidder: Pubkey,
+    pub bid: u64,
+}
+
+#[event]
+pub struct BidWithdrawn {
+    pub prob_pool: Pubkey,
+    pub listing: Pubkey,
+    pub bidder: Pubkey,
+    pub bid: u64,
+}
+
+#[event]
+pub struct CreatorFeesWithdrawn {
+    pub prob_pool: Pubkey,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct UserWithdrew {
+    pub user: Pubkey,
+    pub ptoken_mint: Pubkey,
+    pub ptoken_amount: u64,
+}
+
+#[event]
+pub struct RefundStarted {
+    pub prob_pool: Pubkey,
+    pub refund_lamports: u64,
+    pub refund_ptokens: u64,
+    pub redeem_deadline: i64,
+}
+
+#[event]
+pub struct Redeemed {
+    pub prob_pool: Pubkey,
+    pub user: Pubkey,
+    pub ptoken_amount: u64,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct PoolClosed {
+    pub prob_pool: Pubkey,
+    pub authority: Pubkey,
+    pub lamports: u64,
+    pub prizes_claimed: u8,
+}
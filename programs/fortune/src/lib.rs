@@ -1,17 +1,43 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token;
 use anchor_spl::token::{Mint, Token, TokenAccount};
-use arrayref::array_ref;
 use solana_program::program::invoke_signed;
 use solana_program::sysvar::SysvarId;
-use solana_program::{system_instruction, sysvar};
+use solana_program::{keccak, system_instruction, sysvar};
 use spl_token::instruction::sync_native;
 
 declare_id!("7tSKVgnzdSAStFuDzPjqE7mhCtXrnX9KLTsbJuGrn52C");
 
+mod amm;
+mod curve;
 mod error;
+mod events;
+mod metadata;
 mod random;
 
+// Slots between a burn request and the slot hash that settles it
+pub const BURN_SLOT_DELAY: u64 = 2;
+// Most ptokens a single burn request can commit
+pub const MAX_BURN_AMOUNT: u64 = 9;
+// Slots an oracle has to settle a burn request before the user can cancel it
+pub const BURN_TIMEOUT_SLOTS: u64 = 512;
+// Most prizes a single pool can hold
+pub const MAX_PRIZES: usize = 4;
+// Most collections the protocol allowlist can hold
+pub const MAX_COLLECTIONS: usize = 16;
+// Most separate ticket ranges a raffle entry can hold, contiguous purchases share one
+pub const MAX_ENTRY_RANGES: usize = 8;
+// Longest pool name, metadata URI and category in bytes
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_URI_LEN: usize = 200;
+pub const MAX_CATEGORY_LEN: usize = 32;
+// Creator fee shares are in basis points of the swap and burn fees
+pub const BPS_SCALAR: u64 = 10_000;
+// Seconds holders have to redeem once a refund starts, the creator can close after
+pub const REDEEM_WINDOW: i64 = 30 * 24 * 60 * 60;
+// Shortest time a winner has to claim after trading closes
+pub const MIN_CLAIM_WINDOW: i64 = 24 * 60 * 60;
+
 #[program]
 pub mod fortune {
 
@@ -20,64 +46,263 @@ pub mod fortune {
     // Create program vaults
     pub fn initialize(
         ctx: Context<Initialize>,
-        swap_fee: u64,
-        burn_cost: u64,
-        fee_scalar: u64,
-        lamport_min: u64,
-        lamport_max: u64,
-        ptoken_max: u64,
-        ptoken_min: u64,
+        config: StateConfig,
+        quote_config: QuoteConfig,
     ) -> Result<()> {
+        config.validate()?;
+        quote_config.validate()?;
         // Set state
         let state = &mut ctx.accounts.state;
-        state.burn_cost = burn_cost;
-        state.fee_scalar = fee_scalar;
         state.authority = ctx.accounts.signer.key();
-        state.swap_fee = swap_fee;
-        state.lamport_init_min = lamport_min;
-        state.lamport_init_max = lamport_max;
-        state.ptoken_init_max = ptoken_max;
-        state.ptoken_init_min = ptoken_min;
+        state.set_config(&config);
+        // SOL is always a quote mint
+        let quote = &mut ctx.accounts.quote;
+        quote.mint = ctx.accounts.spl_mint.key();
+        quote.vault = ctx.accounts.spl_vault.key();
+        quote.set_config(&quote_config);
+        // Collection gate starts open
+        ctx.accounts.collections.gated = false;
         Ok(())
     }
 
-    // Create probability pool and its vaults
-    pub fn create_pool(
-        ctx: Context<CreatePool>,
-        lamport_amount: u64,
-        ptoken_amount: u64,
+    // Update protocol parameters
+    pub fn update_config(ctx: Context<UpdateState>, config: StateConfig) -> Result<()> {
+        config.validate()?;
+        ctx.accounts.state.set_config(&config);
+        Ok(())
+    }
+
+    // First step of an authority handover, the new authority has to accept
+    pub fn propose_authority(ctx: Context<UpdateState>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.state.pending_authority = new_authority;
+        Ok(())
+    }
+
+    // Second step of an authority handover
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.authority = ctx.accounts.new_authority.key();
+        state.pending_authority = Pubkey::default();
+        Ok(())
+    }
+
+    // Allow pools to be priced in another SPL mint, creates its protocol fee vault
+    pub fn add_quote_mint(ctx: Context<AddQuoteMint>, config: QuoteConfig) -> Result<()> {
+        config.validate()?;
+        let quote = &mut ctx.accounts.quote;
+        quote.mint = ctx.accounts.quote_mint.key();
+        quote.vault = ctx.accounts.fortune_vault.key();
+        quote.set_config(&config);
+        Ok(())
+    }
+
+    // Update the initial liquidity bounds of a quote mint
+    pub fn update_quote(ctx: Context<UpdateQuote>, config: QuoteConfig) -> Result<()> {
+        config.validate()?;
+        ctx.accounts.quote.set_config(&config);
+        Ok(())
+    }
+
+    // Set the randomness oracle for new burn requests, default pubkey uses slot hashes
+    pub fn set_oracle(ctx: Context<UpdateState>, oracle: Pubkey) -> Result<()> {
+        ctx.accounts.state.oracle = oracle;
+        Ok(())
+    }
+
+    // Replace the collection allowlist, while gated NFT prizes must be in one of the
+    // verified collections
+    pub fn set_collections(
+        ctx: Context<SetCollections>,
+        gated: bool,
+        keys: Vec<Pubkey>,
     ) -> Result<()> {
         require!(
-            lamport_amount >= ctx.accounts.state.lamport_init_min,
+            keys.len() <= MAX_COLLECTIONS,
+            error::FortuneError::CollectionLimit
+        );
+        let collections = &mut ctx.accounts.collections;
+        collections.gated = gated;
+        collections.keys = keys;
+        Ok(())
+    }
+
+    // Withdraw protocol fees of a quote mint
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        require!(
+            amount <= ctx.accounts.fortune_lamport_vault.amount,
+            error::FortuneError::InsufficientFees
+        );
+        let vault_bump = *ctx.bumps.get("fortune_lamport_vault").unwrap();
+        // Transfer fees to recipient
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                    authority: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    ctx.accounts.quote_mint.key().as_ref(),
+                    &[vault_bump],
+                ]],
+            ),
+            amount,
+        )?;
+        ctx.accounts.quote.withdraw(amount)?;
+        Ok(())
+    }
+
+    // Withdraw protocol fees as SOL, unwrapped through a temporary token account
+    pub fn unwrap_fees(ctx: Context<UnwrapFees>, amount: u64) -> Result<()> {
+        require!(
+            amount <= ctx.accounts.fortune_lamport_vault.amount,
+            error::FortuneError::InsufficientFees
+        );
+        // Bumps
+        let vault_bump = *ctx.bumps.get("fortune_lamport_vault").unwrap();
+        let unwrap_vault_bump = *ctx.bumps.get("unwrap_vault").unwrap();
+        // Transfer fees to unwrap vault
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                    to: ctx.accounts.unwrap_vault.to_account_info(),
+                    authority: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    ctx.accounts.native_mint.key().as_ref(),
+                    &[vault_bump],
+                ]],
+            ),
+            amount,
+        )?;
+        // Close unwrap vault, authority gets the fees and its rent back
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_spl::token::CloseAccount {
+                account: ctx.accounts.unwrap_vault.to_account_info(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: ctx.accounts.unwrap_vault.to_account_info(),
+            },
+            &[&[
+                &b"unwrap"[..],
+                ctx.accounts.native_mint.key().as_ref(),
+                &[unwrap_vault_bump],
+            ]],
+        ))?;
+        // Forward the fees to recipient
+        invoke_signed(
+            &system_instruction::transfer(
+                &ctx.accounts.authority.key(),
+                &ctx.accounts.recipient.key(),
+                amount,
+            ),
+            &[
+                ctx.accounts.authority.to_account_info(),
+                ctx.accounts.recipient.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            &[],
+        )?;
+        ctx.accounts.quote.withdraw(amount)?;
+        Ok(())
+    }
+
+    // Create probability pool and its vaults
+    pub fn create_pool(ctx: Context<CreatePool>, config: PoolConfig) -> Result<()> {
+        require!(
+            config.lamport_amount >= ctx.accounts.quote.lamport_init_min,
             error::FortuneError::LamportInitMin
         );
         require!(
-            lamport_amount < ctx.accounts.state.lamport_init_max,
+            config.lamport_amount < ctx.accounts.quote.lamport_init_max,
             error::FortuneError::LamportInitMax
         );
         require!(
-            ptoken_amount < ctx.accounts.state.ptoken_init_max,
+            config.ptoken_amount < ctx.accounts.state.ptoken_init_max,
             error::FortuneError::PtokenInitMax
         );
         require!(
-            ptoken_amount >= ctx.accounts.state.ptoken_init_min,
+            config.ptoken_amount >= ctx.accounts.state.ptoken_init_min,
             error::FortuneError::PtokenInitMin
         );
+        // Trading window ends in the future, claims and refunds come after it
+        require!(
+            config.open_until > Clock::get()?.unix_timestamp,
+            error::FortuneError::InvalidDeadline
+        );
+        require!(
+            config.refund_deadline >= config.open_until,
+            error::FortuneError::InvalidDeadline
+        );
+        if let Some(claim_deadline) = config.claim_deadline {
+            require!(
+                claim_deadline
+                    >= config
+                        .open_until
+                        .checked_add(MIN_CLAIM_WINDOW)
+                        .ok_or(error::FortuneError::MathOverflow)?,
+                error::FortuneError::InvalidDeadline
+            );
+        }
+        // Raffle tickets are sold at a fixed price
+        if config.mode == PoolMode::Raffle {
+            require!(config.ticket_price > 0, error::FortuneError::InvalidAmount);
+            require!(config.reserve == 0, error::FortuneError::WrongPoolMode);
+        }
+        config
+            .prize
+            .validate(&ctx.accounts.nft_mint, ctx.accounts.nft_account.amount)?;
+        ctx.accounts
+            .collections
+            .check(&ctx.accounts.nft_mint, &ctx.accounts.nft_metadata)?;
+        config.metadata.validate(&ctx.accounts.state)?;
+        config.limits.validate()?;
         // Set pool data
         ctx.accounts.prob_pool.authority = ctx.accounts.signer.key();
-        ctx.accounts.prob_pool.nft_authority = ctx.accounts.signer.key();
         ctx.accounts.prob_pool.lamport_vault = ctx.accounts.lamport_vault.key();
         ctx.accounts.prob_pool.ptoken_vault = ctx.accounts.ptoken_vault.key();
         ctx.accounts.prob_pool.lamport_vault = ctx.accounts.lamport_vault.key();
         ctx.accounts.prob_pool.ptoken_vault = ctx.accounts.ptoken_vault.key();
         ctx.accounts.prob_pool.ptoken_mint = ctx.accounts.ptoken_mint.key();
-        ctx.accounts.prob_pool.nft_mint = ctx.accounts.nft_mint.key();
+        ctx.accounts.prob_pool.quote_mint = ctx.accounts.quote_mint.key();
+        ctx.accounts.prob_pool.prizes = vec![Prize {
+            mint: ctx.accounts.nft_mint.key(),
+            kind: config.prize.kind,
+            amount: config.prize.amount,
+            weight: config.prize.weight,
+            status: PrizeStatus::Open,
+            winner: Pubkey::default(),
+        }];
         // Set pool params
-        ctx.accounts.prob_pool.claimed = false;
-        ctx.accounts.prob_pool.to_claim = false;
-        ctx.accounts.prob_pool.ptoken_supply = ptoken_amount;
-        ctx.accounts.prob_pool.lamport_supply = lamport_amount;
+        ctx.accounts.prob_pool.ptoken_supply = config.ptoken_amount;
+        ctx.accounts.prob_pool.initial_ptoken_supply = config.ptoken_amount;
+        ctx.accounts.prob_pool.odds_model = config.odds_model;
+        ctx.accounts.prob_pool.lamport_supply = config.lamport_amount;
         ctx.accounts.prob_pool.outstanding_ptokens = 0;
+        ctx.accounts.prob_pool.open_until = config.open_until;
+        ctx.accounts.prob_pool.claim_deadline = config.claim_deadline;
+        ctx.accounts.prob_pool.refund_deadline = config.refund_deadline;
+        ctx.accounts.prob_pool.refunding = false;
+        ctx.accounts.prob_pool.mode = config.mode;
+        ctx.accounts.prob_pool.ticket_price = config.ticket_price;
+        ctx.accounts.prob_pool.entries = 0;
+        ctx.accounts.prob_pool.swap_fee = config.metadata.swap_fee;
+        ctx.accounts.prob_pool.burn_cost = config.metadata.burn_cost;
+        ctx.accounts.prob_pool.seed_shares = config.lamport_amount;
+        ctx.accounts.prob_pool.lp_shares = 0;
+        ctx.accounts.prob_pool.curve = config.curve;
+        ctx.accounts.prob_pool.initial_lamport_supply = config.lamport_amount;
+        ctx.accounts.prob_pool.reserve = config.reserve;
+        ctx.accounts.prob_pool.limits = config.limits;
+        // Set pool metadata
+        let prob_pool = ctx.accounts.prob_pool.key();
+        ctx.accounts.pool_metadata.prob_pool = prob_pool;
+        ctx.accounts.pool_metadata.set(&config.metadata);
 
         let ptoken_mint_bump = *ctx.bumps.get("ptoken_mint").unwrap();
 
@@ -96,459 +321,3404 @@ pub mod fortune {
                     &[ptoken_mint_bump],
                 ]],
             ),
-            ptoken_amount,
+            config.ptoken_amount,
         )?;
 
-        // Transfer nft to vault
+        // Transfer prize to vault
+        deposit_prize(
+            &config.prize,
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.nft_vault.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+        emit!(events::PoolCreated {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            authority: ctx.accounts.signer.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            ptoken_mint: ctx.accounts.ptoken_mint.key(),
+            quote_mint: ctx.accounts.quote_mint.key(),
+            lamport_supply: config.lamport_amount,
+            ptoken_supply: config.ptoken_amount,
+            open_until: config.open_until,
+            claim_deadline: config.claim_deadline,
+            refund_deadline: config.refund_deadline,
+            odds_model: config.odds_model,
+            curve: config.curve,
+            reserve: config.reserve,
+            limits: config.limits,
+            mode: config.mode,
+            ticket_price: config.ticket_price,
+            prize_kind: config.prize.kind,
+            prize_amount: config.prize.amount,
+            prize_weight: config.prize.weight,
+        });
+        Ok(())
+    }
+
+    // Rename the pool or change its fee overrides, the pool authority only
+    pub fn update_pool_metadata(
+        ctx: Context<UpdatePoolMetadata>,
+        metadata: PoolMetadataConfig,
+    ) -> Result<()> {
+        metadata.validate(&ctx.accounts.state)?;
+        ctx.accounts.prob_pool.swap_fee = metadata.swap_fee;
+        ctx.accounts.prob_pool.burn_cost = metadata.burn_cost;
+        ctx.accounts.pool_metadata.set(&metadata);
+        emit!(events::PoolMetadataUpdated {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            name: metadata.name,
+            uri: metadata.uri,
+            category: metadata.category,
+            swap_fee: metadata.swap_fee,
+            burn_cost: metadata.burn_cost,
+        });
+        Ok(())
+    }
+
+    // Withdraw the creator's share of a pool's fees in one mint, the vault is closed
+    // once the pool is gone and it is emptied
+    pub fn withdraw_creator_fees(ctx: Context<WithdrawCreatorFees>, amount: u64) -> Result<()> {
+        require!(
+            amount <= ctx.accounts.creator_vault.amount,
+            error::FortuneError::InsufficientFees
+        );
+        let vault_bump = *ctx.bumps.get("creator_vault").unwrap();
+        let mint = ctx.accounts.mint.key();
+        let prob_pool = ctx.accounts.prob_pool.key();
+        let authority = ctx.accounts.authority.key();
+        let seeds: &[&[u8]] = &[
+            &b"creator"[..],
+            mint.as_ref(),
+            prob_pool.as_ref(),
+            authority.as_ref(),
+            &[vault_bump],
+        ];
         token::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 anchor_spl::token::Transfer {
-                    from: ctx.accounts.nft_account.to_account_info(),
-                    to: ctx.accounts.nft_vault.to_account_info(),
-                    authority: ctx.accounts.signer.to_account_info(),
+                    from: ctx.accounts.creator_vault.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                    authority: ctx.accounts.creator_vault.to_account_info(),
                 },
-                &[],
+                &[seeds],
             ),
-            1,
+            amount,
         )?;
+        // Closed pools earn no more fees
+        if *ctx.accounts.prob_pool.owner != crate::ID && amount == ctx.accounts.creator_vault.amount
+        {
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::CloseAccount {
+                    account: ctx.accounts.creator_vault.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: ctx.accounts.creator_vault.to_account_info(),
+                },
+                &[seeds],
+            ))?;
+        }
+        emit!(events::CreatorFeesWithdrawn {
+            prob_pool,
+            authority,
+            mint,
+            amount,
+        });
         Ok(())
     }
 
-    // Swap SPL for ptokens
-    pub fn buy(ctx: Context<Buy>, ptoken_amount: u64) -> Result<()> {
-        // Prob pool is active
+    // Put another prize up for the same pool, a winning burn takes one of the open
+    // prizes picked by weight
+    pub fn add_prize(ctx: Context<AddPrize>, prize: PrizeConfig) -> Result<()> {
+        let prob_pool = &ctx.accounts.prob_pool;
         require!(
-            ctx.accounts.prob_pool.claimed == false,
+            !prob_pool.refunding && Clock::get()?.unix_timestamp < prob_pool.open_until,
             error::FortuneError::PoolClosed
         );
+        // Raffles draw a single winner
         require!(
-            ctx.accounts.prob_pool.ptoken_supply > 1,
-            error::FortuneError::SoldOut
+            prob_pool.mode == PoolMode::Continuous,
+            error::FortuneError::WrongPoolMode
         );
-        msg!("swap_fee: {:?}", ctx.accounts.state.swap_fee);
-        msg!("scalar: {:?}", ctx.accounts.state.fee_scalar);
-        // Calculate new AMM token supply, costs, and fees
-        let k = ctx.accounts.prob_pool.ptoken_supply * ctx.accounts.prob_pool.lamport_supply;
-        msg!("k: {:?}", k);
-        let new_ptoken_supply = ctx.accounts.prob_pool.ptoken_supply - ptoken_amount;
-        msg!("new_ptoken_suppl: {:?}", new_ptoken_supply);
-        let new_spl_supply = k / new_ptoken_supply;
-        msg!("new_spl_supply: {:?}", new_spl_supply);
-        let spl_cost = new_spl_supply - ctx.accounts.prob_pool.lamport_supply;
-        let spl_fee = (spl_cost * ctx.accounts.state.swap_fee) / ctx.accounts.state.fee_scalar;
-        msg!("fee: {:?}", spl_fee);
-        msg!("cost: {:?}", spl_cost);
-
-        let pool_token_bump = *ctx.bumps.get("pool_ptoken_vault").unwrap();
-
-        // Transfer spl cost to pool lamport vault
-        invoke_signed(
-            &system_instruction::transfer(
-                &ctx.accounts.signer.key(),
-                &ctx.accounts.pool_lamport_vault.key(),
-                spl_cost,
-            ),
-            &[
-                ctx.accounts.signer.to_account_info(),
-                ctx.accounts.pool_lamport_vault.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[],
+        require!(
+            prob_pool.prizes.len() < MAX_PRIZES,
+            error::FortuneError::PrizeLimit
+        );
+        prize.validate(&ctx.accounts.nft_mint, ctx.accounts.nft_account.amount)?;
+        ctx.accounts
+            .collections
+            .check(&ctx.accounts.nft_mint, &ctx.accounts.nft_metadata)?;
+        // Transfer prize to vault
+        deposit_prize(
+            &prize,
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.nft_vault.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
         )?;
-        // Sync native
-        let ix_1 = sync_native(
-            &ctx.accounts.token_program.key(),
-            &ctx.accounts.pool_lamport_vault.key(),
+        ctx.accounts.prob_pool.prizes.push(Prize {
+            mint: ctx.accounts.nft_mint.key(),
+            kind: prize.kind,
+            amount: prize.amount,
+            weight: prize.weight,
+            status: PrizeStatus::Open,
+            winner: Pubkey::default(),
+        });
+        emit!(events::PrizeAdded {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            mint: ctx.accounts.nft_mint.key(),
+            kind: prize.kind,
+            amount: prize.amount,
+            weight: prize.weight,
+        });
+        Ok(())
+    }
+
+    // Deposit quote liquidity into the curve for LP shares, ptokens are minted into the
+    // pool so the price holds
+    pub fn add_liquidity(
+        ctx: Context<AddLiquidity>,
+        lamports: u64,
+        min_shares_out: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .prob_pool
+            .check_market(Clock::get()?.unix_timestamp)?;
+        // Other curves price off the ptokens sold, minting into the pool would move them
+        require!(
+            ctx.accounts.prob_pool.curve == Curve::ConstantProduct,
+            error::FortuneError::WrongCurve
+        );
+        // Deposits would count towards the reserve
+        require!(
+            ctx.accounts.prob_pool.reserve == 0,
+            error::FortuneError::ReserveLiquidity
+        );
+        // New shares buy into the fees earlier providers earned at their current value
+        let liquidity = amm::add_liquidity(
+            ctx.accounts.prob_pool.ptoken_supply,
+            ctx.accounts.prob_pool.lamport_supply,
+            ctx.accounts.prob_pool.total_shares(),
+            ctx.accounts.prob_pool.lp_shares,
+            ctx.accounts.lp_vault.amount,
+            lamports,
         )?;
-        invoke_signed(
-            &ix_1,
-            &[
+        require!(
+            liquidity.shares >= min_shares_out,
+            error::FortuneError::SlippageExceeded
+        );
+        // Bumps
+        let ptoken_mint_bump = *ctx.bumps.get("ptoken_mint").unwrap();
+        let lp_mint_bump = *ctx.bumps.get("lp_mint").unwrap();
+        let prob_pool = ctx.accounts.prob_pool.key();
+        // Transfer liquidity to pool lamport vault
+        token::transfer(
+            CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.pool_lamport_vault.to_account_info(),
-            ],
-            &[],
-        )?;
-        // Transfer fees to fortune vault
-        invoke_signed(
-            &system_instruction::transfer(
-                &ctx.accounts.signer.key(),
-                &ctx.accounts.fortune_lamport_vault.key(),
-                spl_fee,
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_quote_account.to_account_info(),
+                    to: ctx.accounts.pool_lamport_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
             ),
-            &[
-                ctx.accounts.signer.to_account_info(),
-                ctx.accounts.fortune_lamport_vault.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[],
-        )?;
-        // Sync native
-        let ix_2 = sync_native(
-            &ctx.accounts.token_program.key(),
-            &ctx.accounts.fortune_lamport_vault.key(),
-        )?;
-        invoke_signed(
-            &ix_2,
-            &[
-                ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.fortune_lamport_vault.to_account_info(),
-            ],
-            &[],
+            liquidity.lamports,
         )?;
-        // Transfer ptokens to prob pool user vault
-        token::transfer(
+        // Transfer the fee buy-in to the LP vault
+        if liquidity.fees > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: ctx.accounts.user_quote_account.to_account_info(),
+                        to: ctx.accounts.lp_vault.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                ),
+                liquidity.fees,
+            )?;
+        }
+        // Mint ptokens to pool vault
+        if liquidity.ptokens > 0 {
+            anchor_spl::token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::MintTo {
+                        mint: ctx.accounts.ptoken_mint.to_account_info(),
+                        to: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                        authority: ctx.accounts.ptoken_mint.to_account_info(),
+                    },
+                    &[&[&b"mint"[..], prob_pool.as_ref(), &[ptoken_mint_bump]]],
+                ),
+                liquidity.ptokens,
+            )?;
+        }
+        // Mint LP shares to provider
+        anchor_spl::token::mint_to(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                anchor_spl::token::Transfer {
-                    from: ctx.accounts.pool_ptoken_vault.to_account_info(),
-                    to: ctx.accounts.user_ptoken_vault.to_account_info(),
-                    authority: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                anchor_spl::token::MintTo {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.lp_account.to_account_info(),
+                    authority: ctx.accounts.lp_mint.to_account_info(),
                 },
-                &[&[
-                    &b"vault"[..],
-                    &ctx.accounts.ptoken_mint.key().as_ref(),
-                    &ctx.accounts.prob_pool.key().as_ref(),
-                    &[pool_token_bump],
-                ]],
+                &[&[&b"lp"[..], prob_pool.as_ref(), &[lp_mint_bump]]],
             ),
-            ptoken_amount,
+            liquidity.shares,
         )?;
         // Set prob pool data
-        ctx.accounts.prob_pool.ptoken_supply = new_ptoken_supply;
-        ctx.accounts.prob_pool.lamport_supply = new_spl_supply;
-        ctx.accounts.prob_pool.outstanding_ptokens += ptoken_amount;
+        ctx.accounts.prob_pool.ptoken_supply = liquidity.ptoken_supply;
+        ctx.accounts.prob_pool.lamport_supply = liquidity.lamport_supply;
+        ctx.accounts.prob_pool.lp_shares += liquidity.shares;
+        emit!(events::LiquidityAdded {
+            prob_pool,
+            provider: ctx.accounts.signer.key(),
+            lamports: liquidity.lamports,
+            fees: liquidity.fees,
+            ptokens: liquidity.ptokens,
+            shares: liquidity.shares,
+            ptoken_supply: liquidity.ptoken_supply,
+            lamport_supply: liquidity.lamport_supply,
+        });
         Ok(())
     }
 
-    pub fn request_burn(ctx: Context<RequestBurn>, ptoken_amount: u64) -> Result<()> {
-        // Bump
-        let user_ptoken_vault_bump = *ctx.bumps.get("user_ptoken_vault").unwrap();
-        // Transfer from user vault to user burn
-        token::transfer(
-            CpiContext::new_with_signer(
+    // Burn LP shares for their part of the curve and of the fees earned, ptokens are
+    // burnt from the pool so the price holds
+    pub fn remove_liquidity(
+        ctx: Context<RemoveLiquidity>,
+        shares: u64,
+        min_lamports_out: u64,
+    ) -> Result<()> {
+        // Refunding pools set the LP claim aside, see redeem_lp
+        require!(
+            !ctx.accounts.prob_pool.refunding,
+            error::FortuneError::PoolClosed
+        );
+        require!(
+            shares <= ctx.accounts.prob_pool.lp_shares,
+            error::FortuneError::InsufficientLiquidity
+        );
+        let liquidity = amm::remove_liquidity(
+            ctx.accounts.prob_pool.ptoken_supply,
+            ctx.accounts.prob_pool.lamport_supply,
+            ctx.accounts.prob_pool.total_shares(),
+            ctx.accounts.prob_pool.lp_shares,
+            ctx.accounts.lp_vault.amount,
+            shares,
+        )?;
+        require!(
+            liquidity.lamports <= ctx.accounts.pool_lamport_vault.amount,
+            error::FortuneError::InsufficientLiquidity
+        );
+        // Fees earned so far are split by shares
+        let fees = liquidity.fees;
+        require!(
+            liquidity.lamports + fees >= min_lamports_out,
+            error::FortuneError::SlippageExceeded
+        );
+        // Bumps
+        let lamport_vault_bump = *ctx.bumps.get("pool_lamport_vault").unwrap();
+        let ptoken_vault_bump = *ctx.bumps.get("pool_ptoken_vault").unwrap();
+        let lp_vault_bump = *ctx.bumps.get("lp_vault").unwrap();
+        let prob_pool = ctx.accounts.prob_pool.key();
+        // Burn LP shares
+        token::burn(
+            CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                anchor_spl::token::Transfer {
-                    from: ctx.accounts.user_ptoken_vault.to_account_info(),
-                    to: ctx.accounts.user_burn.to_account_info(),
-                    authority: ctx.accounts.user_ptoken_vault.to_account_info(),
+                anchor_spl::token::Burn {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.lp_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
                 },
-                &[&[
-                    &b"vault"[..],
-                    &ctx.accounts.ptoken_mint.key().as_ref(),
-                    &ctx.accounts.signer.key().as_ref(),
-                    &[user_ptoken_vault_bump],
-                ]],
             ),
-            ptoken_amount,
+            shares,
         )?;
-        // Pay burn fees
-        invoke_signed(
-            &system_instruction::transfer(
-                &ctx.accounts.signer.key(),
-                &ctx.accounts.fortune_lamport_vault.key(),
-                ctx.accounts.state.burn_cost,
-            ),
+        // Burn ptokens in pool vault
+        if liquidity.ptokens > 0 {
+            token::burn(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Burn {
+                        mint: ctx.accounts.ptoken_mint.to_account_info(),
+                        to: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                        authority: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                    },
+                    &[&[
+                        &b"vault"[..],
+                        ctx.accounts.ptoken_mint.key().as_ref(),
+                        prob_pool.as_ref(),
+                        &[ptoken_vault_bump],
+                    ]],
+                ),
+                liquidity.ptokens,
+            )?;
+        }
+        // Transfer liquidity and fees to provider
+        vault_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.pool_lamport_vault.to_account_info(),
+            ctx.accounts.recipient.to_account_info(),
             &[
-                ctx.accounts.signer.to_account_info(),
-                ctx.accounts.fortune_lamport_vault.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
+                &b"vault"[..],
+                ctx.accounts.quote_mint.key().as_ref(),
+                prob_pool.as_ref(),
+                &[lamport_vault_bump],
             ],
-            &[],
+            liquidity.lamports,
         )?;
-        // Sync native
-        let ix_2 = sync_native(
-            &ctx.accounts.token_program.key(),
-            &ctx.accounts.fortune_lamport_vault.key(),
+        if fees > 0 {
+            vault_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.lp_vault.to_account_info(),
+                ctx.accounts.recipient.to_account_info(),
+                &[&b"lp_vault"[..], prob_pool.as_ref(), &[lp_vault_bump]],
+                fees,
+            )?;
+        }
+        // Set prob pool data
+        ctx.accounts.prob_pool.ptoken_supply = liquidity.ptoken_supply;
+        ctx.accounts.prob_pool.lamport_supply = liquidity.lamport_supply;
+        ctx.accounts.prob_pool.lp_shares -= shares;
+        emit!(events::LiquidityRemoved {
+            prob_pool,
+            provider: ctx.accounts.signer.key(),
+            lamports: liquidity.lamports,
+            fees,
+            ptokens: liquidity.ptokens,
+            shares,
+            ptoken_supply: liquidity.ptoken_supply,
+            lamport_supply: liquidity.lamport_supply,
+        });
+        Ok(())
+    }
+
+    // Burn LP shares for a pro-rata part of the LP vault once the pool is refunding or
+    // closed, the LP claim on the curve was moved there
+    pub fn redeem_lp(ctx: Context<RedeemLp>, shares: u64) -> Result<()> {
+        if *ctx.accounts.prob_pool.owner == crate::ID {
+            let prob_pool = Account::<ProbPool>::try_from(&ctx.accounts.prob_pool)?;
+            require!(prob_pool.refunding, error::FortuneError::NotRefunding);
+        }
+        let lamports = amm::pro_rata(
+            shares,
+            ctx.accounts.lp_vault.amount,
+            ctx.accounts.lp_mint.supply,
         )?;
-        invoke_signed(
-            &ix_2,
-            &[
+        let lp_vault_bump = *ctx.bumps.get("lp_vault").unwrap();
+        let prob_pool = ctx.accounts.prob_pool.key();
+        // Burn LP shares
+        token::burn(
+            CpiContext::new(
                 ctx.accounts.token_program.to_account_info(),
-                ctx.accounts.fortune_lamport_vault.to_account_info(),
-            ],
-            &[],
+                anchor_spl::token::Burn {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.lp_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            shares,
         )?;
+        vault_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.lp_vault.to_account_info(),
+            ctx.accounts.recipient.to_account_info(),
+            &[&b"lp_vault"[..], prob_pool.as_ref(), &[lp_vault_bump]],
+            lamports,
+        )?;
+        emit!(events::LpRedeemed {
+            prob_pool,
+            provider: ctx.accounts.signer.key(),
+            shares,
+            lamports,
+        });
         Ok(())
     }
 
-    pub fn user_withdraw(ctx: Context<UserWithdraw>, token_amount: u64) -> Result<()> {
-        // Bump
-        let vault_bump = *ctx.bumps.get("user_ptoken_vault").unwrap();
-        // Transfer from user vault to user account
+    // Swap SPL for ptokens, fills up to ptoken_amount from what is left in the pool
+    pub fn buy(
+        ctx: Context<Buy>,
+        ptoken_amount: u64,
+        max_lamports_in: u64,
+        min_ptokens_out: u64,
+    ) -> Result<()> {
+        // Prob pool is active
+        require!(
+            ctx.accounts.prob_pool.prizes_left(),
+            error::FortuneError::PoolClosed
+        );
+        require!(
+            !ctx.accounts.prob_pool.refunding,
+            error::FortuneError::PoolClosed
+        );
+        require!(
+            Clock::get()?.unix_timestamp < ctx.accounts.prob_pool.open_until,
+            error::FortuneError::PoolExpired
+        );
+        // Raffle pools sell fixed-price tickets instead
+        require!(
+            ctx.accounts.prob_pool.mode == PoolMode::Continuous,
+            error::FortuneError::WrongPoolMode
+        );
+        require!(
+            ctx.accounts.prob_pool.ptoken_supply > 1,
+            error::FortuneError::SoldOut
+        );
+        // Wallet limit caps the fill like the supply does
+        let allowance = ctx
+            .accounts
+            .prob_pool
+            .limits
+            .wallet_allowance(&ctx.accounts.user_stats);
+        require!(allowance > 0, error::FortuneError::WalletLimit);
+        // Pool always keeps one ptoken
+        let ptoken_amount = ptoken_amount
+            .min(ctx.accounts.prob_pool.ptoken_supply - 1)
+            .min(allowance);
+        require!(
+            ptoken_amount >= min_ptokens_out,
+            error::FortuneError::SlippageExceeded
+        );
+        // Calculate new AMM token supply, costs, and fees
+        let swap = curve::buy(
+            ctx.accounts.prob_pool.curve,
+            ctx.accounts.prob_pool.initial_ptoken_supply,
+            ctx.accounts.prob_pool.initial_lamport_supply,
+            ctx.accounts.prob_pool.ptoken_supply,
+            ctx.accounts.prob_pool.lamport_supply,
+            ptoken_amount,
+        )?;
+        let spl_cost = swap.lamports;
+        let spl_fee = amm::fee(
+            spl_cost,
+            ctx.accounts.prob_pool.swap_fee(&ctx.accounts.state),
+            ctx.accounts.state.fee_scalar,
+        )?;
+        require!(
+            spl_cost
+                .checked_add(spl_fee)
+                .ok_or(error::FortuneError::MathOverflow)?
+                <= max_lamports_in,
+            error::FortuneError::SlippageExceeded
+        );
+
+        let pool_token_bump = *ctx.bumps.get("pool_ptoken_vault").unwrap();
+
+        // Transfer spl cost to pool lamport vault
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_quote_account.to_account_info(),
+                    to: ctx.accounts.pool_lamport_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            spl_cost,
+        )?;
+        // Liquidity providers' share of the fee
+        let lp_fee = ctx.accounts.prob_pool.lp_fee(spl_fee)?;
+        if lp_fee > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: ctx.accounts.user_quote_account.to_account_info(),
+                        to: ctx.accounts.lp_vault.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                ),
+                lp_fee,
+            )?;
+        }
+        // Transfer fees to fortune vault, less the creator's share
+        let creator_fee = ctx.accounts.state.creator_fee(spl_fee - lp_fee)?;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_quote_account.to_account_info(),
+                    to: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            spl_fee - lp_fee - creator_fee,
+        )?;
+        if creator_fee > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: ctx.accounts.user_quote_account.to_account_info(),
+                        to: ctx.accounts.creator_vault.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                ),
+                creator_fee,
+            )?;
+        }
+        // Transfer ptokens to prob pool user vault
         token::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 anchor_spl::token::Transfer {
-                    from: ctx.accounts.user_ptoken_vault.to_account_info(),
-                    to: ctx.accounts.user_account.to_account_info(),
-                    authority: ctx.accounts.user_ptoken_vault.to_account_info(),
+                    from: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                    to: ctx.accounts.user_ptoken_vault.to_account_info(),
+                    authority: ctx.accounts.pool_ptoken_vault.to_account_info(),
                 },
                 &[&[
                     &b"vault"[..],
                     &ctx.accounts.ptoken_mint.key().as_ref(),
-                    &ctx.accounts.signer.key().as_ref(),
-                    &[vault_bump],
+                    &ctx.accounts.prob_pool.key().as_ref(),
+                    &[pool_token_bump],
                 ]],
             ),
-            token_amount,
+            ptoken_amount,
         )?;
-        // // Close empty accounts
-        // if ctx.accounts.user_ptoken_vault.amount == 0 {
-        //     token::close_account(CpiContext::new_with_signer(
-        //         ctx.accounts.token_program.to_account_info(),
-        //         anchor_spl::token::CloseAccount {
-        //             account: ctx.accounts.user_ptoken_vault.to_account_info(),
-        //             destination: ctx.accounts.signer.to_account_info(),
-        //             authority: ctx.accounts.user_ptoken_vault.to_account_info(),
-        //         },
-        //         &[&[
-        //             &b"vault"[..],
-        //             &ctx.accounts.vault_mint.key().as_ref(),
-        //             &ctx.accounts.signer.key().as_ref(),
-        //             &[vault_bump],
-        //         ]],
-        //     ))?;
-        // }
+        // Set prob pool data
+        ctx.accounts.prob_pool.ptoken_supply = swap.ptoken_supply;
+        ctx.accounts.prob_pool.lamport_supply = swap.lamport_supply;
+        ctx.accounts.prob_pool.outstanding_ptokens = ctx
+            .accounts
+            .prob_pool
+            .outstanding_ptokens
+            .checked_add(ptoken_amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.record_bought(
+            ctx.accounts.prob_pool.key(),
+            ctx.accounts.signer.key(),
+            ptoken_amount,
+        )?;
+        user_stats.pool_ptokens = user_stats
+            .pool_ptokens
+            .checked_add(ptoken_amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        ctx.accounts.quote.collect(spl_fee - lp_fee - creator_fee)?;
+        emit!(events::Bought {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.signer.key(),
+            ptoken_amount,
+            lamports: spl_cost,
+            fee: spl_fee,
+            creator_fee,
+            lp_fee,
+            ptoken_supply: swap.ptoken_supply,
+            lamport_supply: swap.lamport_supply,
+        });
         Ok(())
     }
 
-    // Burn ptokens in order to try to win the asset
-    pub fn execute_burn(ctx: Context<ExecuteBurn>, burn_amount: u64) -> Result<()> {
-        // Bump
-        let user_burn_bump = *ctx.bumps.get("user_burn").unwrap();
-        // Burn ptokens in user burn
-        token::burn(
+    // Swap ptokens back into the pool for SPL
+    pub fn sell(ctx: Context<Sell>, ptoken_amount: u64) -> Result<()> {
+        // Prob pool is active
+        require!(
+            ctx.accounts.prob_pool.prizes_left() && !ctx.accounts.prob_pool.refunding,
+            error::FortuneError::PoolClosed
+        );
+        // Raffle tickets cannot be sold back
+        require!(
+            ctx.accounts.prob_pool.mode == PoolMode::Continuous,
+            error::FortuneError::WrongPoolMode
+        );
+        // Calculate new AMM token supply, proceeds, and fees
+        let swap = curve::sell(
+            ctx.accounts.prob_pool.curve,
+            ctx.accounts.prob_pool.initial_ptoken_supply,
+            ctx.accounts.prob_pool.initial_lamport_supply,
+            ctx.accounts.prob_pool.ptoken_supply,
+            ctx.accounts.prob_pool.lamport_supply,
+            ptoken_amount,
+        )?;
+        let spl_proceeds = swap.lamports;
+        let spl_fee = amm::fee(
+            spl_proceeds,
+            ctx.accounts.prob_pool.swap_fee(&ctx.accounts.state),
+            ctx.accounts.state.fee_scalar,
+        )?;
+        require!(
+            spl_proceeds <= ctx.accounts.pool_lamport_vault.amount,
+            error::FortuneError::InsufficientLiquidity
+        );
+        ctx.accounts
+            .prob_pool
+            .check_reserve_withdrawal(spl_proceeds)?;
+
+        // Bumps
+        let user_ptoken_vault_bump = *ctx.bumps.get("user_ptoken_vault").unwrap();
+        let lamport_vault_bump = *ctx.bumps.get("pool_lamport_vault").unwrap();
+
+        // Transfer ptokens from user vault back to prob pool vault
+        token::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                anchor_spl::token::Burn {
-                    mint: ctx.accounts.ptoken_mint.to_account_info(),
-                    to: ctx.accounts.user_burn.to_account_info(),
-                    authority: ctx.accounts.user_burn.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_ptoken_vault.to_account_info(),
+                    to: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                    authority: ctx.accounts.user_ptoken_vault.to_account_info(),
                 },
                 &[&[
-                    &b"burn"[..],
-                    &ctx.accounts.prob_pool.key().as_ref(),
-                    &ctx.accounts.user.key().as_ref(),
-                    &[user_burn_bump],
+                    &b"vault"[..],
+                    ctx.accounts.ptoken_mint.key().as_ref(),
+                    ctx.accounts.signer.key().as_ref(),
+                    &[user_ptoken_vault_bump],
                 ]],
             ),
-            burn_amount,
+            ptoken_amount,
         )?;
-
-        let data = ctx.accounts.slot_hashes.try_borrow_data()?;
-        let most_recent = array_ref![data, 8 + 8, 8];
-
-        let mut rng = u64::from_le_bytes(*most_recent);
-        rng = rng % ctx.accounts.prob_pool.ptoken_supply;
-
-        msg!("rng: {:?}", rng);
-        msg!(
-            "ptoken burn: {:?}",
-            ctx.accounts.prob_pool.ptoken_supply - burn_amount
-        );
-
-        // rng = ctx.accounts.prob_pool.ptoken_supply; // REMOVE: TESTING ONLY
-
-        // P(win) = P(X < burn_amount) = 1-P(X >= burn_amount)
-        if rng >= (ctx.accounts.prob_pool.ptoken_supply - burn_amount) {
-            // Transfer nft to user
-            ctx.accounts.prob_pool.nft_authority = ctx.accounts.user.key();
-            ctx.accounts.prob_pool.to_claim = true;
-        }
-        // Update prob pool data
-        ctx.accounts.prob_pool.outstanding_ptokens -= burn_amount;
-        Ok(())
-    }
-
-    // Claim underlying asset
-    pub fn claim_asset(ctx: Context<ClaimAsset>) -> Result<()> {
-        // Bump
-        let nft_vault_bump = *ctx.bumps.get("nft_vault").unwrap();
-        // Creator cannot claim
-        require!(
-            ctx.accounts.prob_pool.to_claim == true,
-            error::FortuneError::NoClaim
-        );
-        // Transfer nft to claimer
+        // Transfer spl proceeds minus fees to seller
         token::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 anchor_spl::token::Transfer {
-                    from: ctx.accounts.nft_vault.to_account_info(),
-                    to: ctx.accounts.nft_account.to_account_info(),
-                    authority: ctx.accounts.nft_vault.to_account_info(),
+                    from: ctx.accounts.pool_lamport_vault.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                    authority: ctx.accounts.pool_lamport_vault.to_account_info(),
                 },
                 &[&[
                     &b"vault"[..],
-                    &ctx.accounts.nft_mint.key().as_ref(),
-                    &ctx.accounts.prob_pool.key().as_ref(),
-                    &[nft_vault_bump],
+                    ctx.accounts.quote_mint.key().as_ref(),
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    &[lamport_vault_bump],
                 ]],
             ),
-            1,
+            spl_proceeds - spl_fee,
         )?;
-        // Close pool
-        ctx.accounts.prob_pool.claimed = true;
-        ctx.accounts.prob_pool.to_claim = false;
-        Ok(())
-    }
-
-    // Close a probability pool, requires no outstanding ptokens
-    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
-        // Bumps
-        let nft_vault_bump = *ctx.bumps.get("nft_vault").unwrap();
-        let lamport_vault_bump = *ctx.bumps.get("pool_lamport_vault").unwrap();
-        let ptoken_vault_bump = *ctx.bumps.get("pool_ptoken_vault").unwrap();
-
-        // No outstanding ptokens
-        require!(
-            ctx.accounts.prob_pool.outstanding_ptokens == 0,
-            error::FortuneError::OutstandingProb
-        );
-        // No active claim outstanding. Todo: remove this from close critical path
-        require!(
-            ctx.accounts.prob_pool.to_claim == false,
-            error::FortuneError::ActiveClaim
-        );
-        // NFT was never won
-        if ctx.accounts.prob_pool.claimed == false {
-            // Transfer NFT back to creator
+        // Liquidity providers' share of the fee
+        let lp_fee = ctx.accounts.prob_pool.lp_fee(spl_fee)?;
+        if lp_fee > 0 {
             token::transfer(
                 CpiContext::new_with_signer(
                     ctx.accounts.token_program.to_account_info(),
                     anchor_spl::token::Transfer {
-                        from: ctx.accounts.nft_vault.to_account_info(),
-                        to: ctx.accounts.nft_account.to_account_info(),
-                        authority: ctx.accounts.nft_vault.to_account_info(),
+                        from: ctx.accounts.pool_lamport_vault.to_account_info(),
+                        to: ctx.accounts.lp_vault.to_account_info(),
+                        authority: ctx.accounts.pool_lamport_vault.to_account_info(),
                     },
                     &[&[
                         &b"vault"[..],
-                        &ctx.accounts.nft_mint.key().as_ref(),
-                        &ctx.accounts.prob_pool.key().as_ref(),
-                        &[nft_vault_bump],
+                        ctx.accounts.quote_mint.key().as_ref(),
+                        ctx.accounts.prob_pool.key().as_ref(),
+                        &[lamport_vault_bump],
                     ]],
                 ),
-                1,
+                lp_fee,
             )?;
         }
-        // Transfer pool lamport funds to recipient
+        // Transfer fees to fortune vault, less the creator's share
+        let creator_fee = ctx.accounts.state.creator_fee(spl_fee - lp_fee)?;
         token::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 anchor_spl::token::Transfer {
                     from: ctx.accounts.pool_lamport_vault.to_account_info(),
-                    to: ctx.accounts.recipient.to_account_info(),
+                    to: ctx.accounts.fortune_lamport_vault.to_account_info(),
                     authority: ctx.accounts.pool_lamport_vault.to_account_info(),
                 },
                 &[&[
                     &b"vault"[..],
-                    &ctx.accounts.native_mint.key().as_ref(),
-                    &ctx.accounts.prob_pool.key().as_ref(),
+                    ctx.accounts.quote_mint.key().as_ref(),
+                    ctx.accounts.prob_pool.key().as_ref(),
                     &[lamport_vault_bump],
                 ]],
             ),
-            ctx.accounts.pool_lamport_vault.amount,
+            spl_fee - lp_fee - creator_fee,
         )?;
-        // Burn all ptokens
-        token::burn(
+        if creator_fee > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: ctx.accounts.pool_lamport_vault.to_account_info(),
+                        to: ctx.accounts.creator_vault.to_account_info(),
+                        authority: ctx.accounts.pool_lamport_vault.to_account_info(),
+                    },
+                    &[&[
+                        &b"vault"[..],
+                        ctx.accounts.quote_mint.key().as_ref(),
+                        ctx.accounts.prob_pool.key().as_ref(),
+                        &[lamport_vault_bump],
+                    ]],
+                ),
+                creator_fee,
+            )?;
+        }
+        // Set prob pool data
+        ctx.accounts.prob_pool.ptoken_supply = swap.ptoken_supply;
+        ctx.accounts.prob_pool.lamport_supply = swap.lamport_supply;
+        ctx.accounts.prob_pool.outstanding_ptokens = ctx
+            .accounts
+            .prob_pool
+            .outstanding_ptokens
+            .checked_sub(ptoken_amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        ctx.accounts.quote.collect(spl_fee - lp_fee - creator_fee)?;
+        // Only ptokens bought from the pool free up the allowance, others may be sold too
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.prob_pool = ctx.accounts.prob_pool.key();
+        user_stats.user = ctx.accounts.signer.key();
+        let sold_back = ptoken_amount.min(user_stats.pool_ptokens);
+        user_stats.pool_ptokens -= sold_back;
+        user_stats.ptokens_bought = user_stats.ptokens_bought.saturating_sub(sold_back);
+        emit!(events::Sold {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.signer.key(),
+            ptoken_amount,
+            lamports: spl_proceeds,
+            fee: spl_fee,
+            creator_fee,
+            lp_fee,
+            ptoken_supply: swap.ptoken_supply,
+            lamport_supply: swap.lamport_supply,
+        });
+        Ok(())
+    }
+
+    // Buy fixed-price raffle tickets, fills up to ticket_amount from what is left.
+    // Each purchase records its range of ticket numbers in a new entry.
+    pub fn buy_tickets(
+        ctx: Context<BuyTickets>,
+        ticket_amount: u64,
+        max_lamports_in: u64,
+        min_tickets_out: u64,
+    ) -> Result<()> {
+        // Prob pool is an open raffle
+        require!(
+            ctx.accounts.prob_pool.prizes_left() && !ctx.accounts.prob_pool.refunding,
+            error::FortuneError::PoolClosed
+        );
+        require!(
+            Clock::get()?.unix_timestamp < ctx.accounts.prob_pool.open_until,
+            error::FortuneError::PoolExpired
+        );
+        require!(
+            ctx.accounts.prob_pool.mode == PoolMode::Raffle,
+            error::FortuneError::WrongPoolMode
+        );
+        require!(
+            ctx.accounts.prob_pool.ptoken_supply > 0,
+            error::FortuneError::SoldOut
+        );
+        let allowance = ctx
+            .accounts
+            .prob_pool
+            .limits
+            .wallet_allowance(&ctx.accounts.user_stats);
+        require!(allowance > 0, error::FortuneError::WalletLimit);
+        // Every ticket can be sold
+        let ticket_amount = ticket_amount
+            .min(ctx.accounts.prob_pool.ptoken_supply)
+            .min(allowance);
+        require!(ticket_amount > 0, error::FortuneError::InvalidAmount);
+        require!(
+            ticket_amount >= min_tickets_out,
+            error::FortuneError::SlippageExceeded
+        );
+        let spl_cost = ticket_amount
+            .checked_mul(ctx.accounts.prob_pool.ticket_price)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        let spl_fee = amm::fee(
+            spl_cost,
+            ctx.accounts.prob_pool.swap_fee(&ctx.accounts.state),
+            ctx.accounts.state.fee_scalar,
+        )?;
+        require!(
+            spl_cost
+                .checked_add(spl_fee)
+                .ok_or(error::FortuneError::MathOverflow)?
+                <= max_lamports_in,
+            error::FortuneError::SlippageExceeded
+        );
+
+        let pool_token_bump = *ctx.bumps.get("pool_ptoken_vault").unwrap();
+
+        // Transfer spl cost to pool lamport vault
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_quote_account.to_account_info(),
+                    to: ctx.accounts.pool_lamport_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            spl_cost,
+        )?;
+        // Transfer fees to fortune vault, less the creator's share
+        let creator_fee = ctx.accounts.state.creator_fee(spl_fee)?;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_quote_account.to_account_info(),
+                    to: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            spl_fee - creator_fee,
+        )?;
+        if creator_fee > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: ctx.accounts.user_quote_account.to_account_info(),
+                        to: ctx.accounts.creator_vault.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                ),
+                creator_fee,
+            )?;
+        }
+        // Transfer tickets to prob pool user vault
+        token::transfer(
             CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
-                anchor_spl::token::Burn {
-                    mint: ctx.accounts.ptoken_mint.to_account_info(),
-                    to: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                    to: ctx.accounts.user_ptoken_vault.to_account_info(),
                     authority: ctx.accounts.pool_ptoken_vault.to_account_info(),
                 },
                 &[&[
                     &b"vault"[..],
-                    &ctx.accounts.ptoken_mint.key().as_ref(),
-                    &ctx.accounts.prob_pool.key().as_ref(),
-                    &[ptoken_vault_bump],
+                    ctx.accounts.ptoken_mint.key().as_ref(),
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    &[pool_token_bump],
                 ]],
             ),
-            ctx.accounts.pool_ptoken_vault.amount,
+            ticket_amount,
         )?;
-        // Close pool ptoken vault
-        token::close_account(CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            anchor_spl::token::CloseAccount {
-                account: ctx.accounts.pool_ptoken_vault.to_account_info(),
-                destination: ctx.accounts.signer.to_account_info(),
-                authority: ctx.accounts.pool_ptoken_vault.to_account_info(),
-            },
-            &[&[
-                &b"vault"[..],
-                &ctx.accounts.ptoken_mint.key().as_ref(),
-                &ctx.accounts.prob_pool.key().as_ref(),
-                &[ptoken_vault_bump],
-            ]],
-        ))?;
-        // Close pool lamport vault
-        token::close_account(CpiContext::new_with_signer(
+        // Record the ticket range bought
+        let first_ticket = ctx.accounts.prob_pool.tickets_sold();
+        let entry = &mut ctx.accounts.entry;
+        // Entries count the users holding tickets
+        if entry.ranges.is_empty() {
+            ctx.accounts.prob_pool.entries += 1;
+        }
+        entry.prob_pool = ctx.accounts.prob_pool.key();
+        entry.user = ctx.accounts.signer.key();
+        entry.add(first_ticket, ticket_amount)?;
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.record_bought(
+            ctx.accounts.prob_pool.key(),
+            ctx.accounts.signer.key(),
+            ticket_amount,
+        )?;
+        user_stats.pool_ptokens = user_stats
+            .pool_ptokens
+            .checked_add(ticket_amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        // Set prob pool data
+        ctx.accounts.prob_pool.ptoken_supply -= ticket_amount;
+        ctx.accounts.prob_pool.outstanding_ptokens = ctx
+            .accounts
+            .prob_pool
+            .outstanding_ptokens
+            .checked_add(ticket_amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        ctx.accounts.quote.collect(spl_fee - creator_fee)?;
+        emit!(events::TicketsBought {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.signer.key(),
+            entry: ctx.accounts.entry.key(),
+            first_ticket,
+            tickets: ticket_amount,
+            lamports: spl_cost,
+            fee: spl_fee,
+            creator_fee,
+        });
+        Ok(())
+    }
+
+    pub fn request_burn(
+        ctx: Context<RequestBurn>,
+        ptoken_amount: u64,
+        mode: BurnMode,
+    ) -> Result<()> {
+        // Prob pool is active
+        require!(
+            ctx.accounts.prob_pool.prizes_left() && !ctx.accounts.prob_pool.refunding,
+            error::FortuneError::PoolClosed
+        );
+        require!(
+            Clock::get()?.unix_timestamp < ctx.accounts.prob_pool.open_until,
+            error::FortuneError::PoolExpired
+        );
+        // Raffle pools are settled by a single draw
+        require!(
+            ctx.accounts.prob_pool.mode == PoolMode::Continuous,
+            error::FortuneError::WrongPoolMode
+        );
+        require!(ptoken_amount > 0, error::FortuneError::InvalidAmount);
+        require!(
+            ptoken_amount <= ctx.accounts.prob_pool.limits.max_burn(),
+            error::FortuneError::BurnLimit
+        );
+        require!(
+            ctx.accounts.prob_pool.reserve_met(),
+            error::FortuneError::ReserveNotMet
+        );
+        let burn_cost = ctx.accounts.prob_pool.burn_cost(&ctx.accounts.state);
+        let slot = Clock::get()?.slot;
+        require!(
+            ctx.accounts
+                .prob_pool
+                .limits
+                .burn_cooled_down(&ctx.accounts.user_stats, slot),
+            error::FortuneError::BurnCooldown
+        );
+        // One pending request per user and pool
+        require!(
+            ctx.accounts.burn_request.status != BurnStatus::Pending,
+            error::FortuneError::BurnPending
+        );
+        // Bump
+        let user_ptoken_vault_bump = *ctx.bumps.get("user_ptoken_vault").unwrap();
+        // Transfer from user vault to user burn
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_ptoken_vault.to_account_info(),
+                    to: ctx.accounts.user_burn.to_account_info(),
+                    authority: ctx.accounts.user_ptoken_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    &ctx.accounts.ptoken_mint.key().as_ref(),
+                    &ctx.accounts.signer.key().as_ref(),
+                    &[user_ptoken_vault_bump],
+                ]],
+            ),
+            ptoken_amount,
+        )?;
+        // Pay burn fees, the creator's share to the pool's SOL creator vault
+        let creator_fee = ctx.accounts.state.creator_fee(burn_cost)?;
+        wrap_lamports(
+            ctx.accounts.signer.to_account_info(),
+            ctx.accounts.fortune_lamport_vault.to_account_info(),
+            burn_cost - creator_fee,
             ctx.accounts.token_program.to_account_info(),
-            anchor_spl::token::CloseAccount {
-                account: ctx.accounts.pool_lamport_vault.to_account_info(),
-                destination: ctx.accounts.signer.to_account_info(),
-                authority: ctx.accounts.pool_lamport_vault.to_account_info(),
-            },
-            &[&[
-                &b"vault"[..],
-                &ctx.accounts.native_mint.key().as_ref(),
-                &ctx.accounts.prob_pool.key().as_ref(),
-                &[lamport_vault_bump],
-            ]],
-        ))?;
-        // Close pool nft vault
-        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+        )?;
+        if creator_fee > 0 {
+            wrap_lamports(
+                ctx.accounts.signer.to_account_info(),
+                ctx.accounts.creator_sol_vault.to_account_info(),
+                creator_fee,
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
+        ctx.accounts.quote.collect(burn_cost - creator_fee)?;
+        let user_stats = &mut ctx.accounts.user_stats;
+        user_stats.prob_pool = ctx.accounts.prob_pool.key();
+        user_stats.user = ctx.accounts.signer.key();
+        user_stats.last_burn_slot = slot;
+        // Commit to a future slot and a seed unique to this request
+        let burn_nonce = ctx.accounts.prob_pool.burn_nonce;
+        let burn_request = &mut ctx.accounts.burn_request;
+        burn_request.user = ctx.accounts.signer.key();
+        burn_request.prob_pool = ctx.accounts.prob_pool.key();
+        burn_request.amount = ptoken_amount;
+        burn_request.fee_paid = burn_cost;
+        burn_request.creator_fee = creator_fee;
+        burn_request.status = BurnStatus::Pending;
+        burn_request.mode = mode;
+        burn_request.draws = Vec::new();
+        burn_request.won = false;
+        burn_request.target_slot = slot + BURN_SLOT_DELAY;
+        burn_request.oracle = ctx.accounts.state.oracle;
+        burn_request.seed = keccak::hashv(&[
+            ctx.accounts.prob_pool.key().as_ref(),
+            ctx.accounts.signer.key().as_ref(),
+            &burn_nonce.to_le_bytes(),
+        ])
+        .to_bytes();
+        ctx.accounts.prob_pool.burn_nonce += 1;
+        ctx.accounts.prob_pool.pending_burns += 1;
+        emit!(events::BurnRequested {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.signer.key(),
+            ptoken_amount,
+            burn_cost,
+            creator_fee,
+            target_slot: ctx.accounts.burn_request.target_slot,
+            oracle: ctx.accounts.burn_request.oracle,
+            seed: ctx.accounts.burn_request.seed,
+        });
+        Ok(())
+    }
+
+    pub fn user_withdraw(ctx: Context<UserWithdraw>, token_amount: u64) -> Result<()> {
+        // Raffle tickets stay with their entry until the draw, a closed pool has no draw left
+        if *ctx.accounts.prob_pool.owner == crate::ID {
+            let prob_pool = Account::<ProbPool>::try_from(&ctx.accounts.prob_pool)?;
+            require!(
+                prob_pool.mode != PoolMode::Raffle
+                    || !prob_pool.prizes_left()
+                    || prob_pool.refunding,
+                error::FortuneError::TicketsLocked
+            );
+        }
+        // Bump
+        let vault_bump = *ctx.bumps.get("user_ptoken_vault").unwrap();
+        // Transfer from user vault to user account
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_ptoken_vault.to_account_info(),
+                    to: ctx.accounts.user_account.to_account_info(),
+                    authority: ctx.accounts.user_ptoken_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    &ctx.accounts.ptoken_mint.key().as_ref(),
+                    &ctx.accounts.signer.key().as_ref(),
+                    &[vault_bump],
+                ]],
+            ),
+            token_amount,
+        )?;
+        emit!(events::UserWithdrew {
+            user: ctx.accounts.signer.key(),
+            ptoken_mint: ctx.accounts.ptoken_mint.key(),
+            ptoken_amount: token_amount,
+        });
+        // // Close empty accounts
+        // if ctx.accounts.user_ptoken_vault.amount == 0 {
+        //     token::close_account(CpiContext::new_with_signer(
+        //         ctx.accounts.token_program.to_account_info(),
+        //         anchor_spl::token::CloseAccount {
+        //             account: ctx.accounts.user_ptoken_vault.to_account_info(),
+        //             destination: ctx.accounts.signer.to_account_info(),
+        //             authority: ctx.accounts.user_ptoken_vault.to_account_info(),
+        //         },
+        //         &[&[
+        //             &b"vault"[..],
+        //             &ctx.accounts.vault_mint.key().as_ref(),
+        //             &ctx.accounts.signer.key().as_ref(),
+        //             &[vault_bump],
+        //         ]],
+        //     ))?;
+        // }
+        Ok(())
+    }
+
+    // Escrow ptokens from the user vault in a listing with an ask for the whole amount,
+    // other users fill at the ask or bid for it
+    pub fn list_ptokens(ctx: Context<ListPtokens>, ptoken_amount: u64, ask: u64) -> Result<()> {
+        ctx.accounts
+            .prob_pool
+            .check_market(Clock::get()?.unix_timestamp)?;
+        require!(ptoken_amount > 0, error::FortuneError::InvalidAmount);
+        require!(ask > 0, error::FortuneError::ZeroAsk);
+        // Bump
+        let user_ptoken_vault_bump = *ctx.bumps.get("user_ptoken_vault").unwrap();
+        // Transfer from user vault to listing escrow
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_ptoken_vault.to_account_info(),
+                    to: ctx.accounts.listing_vault.to_account_info(),
+                    authority: ctx.accounts.user_ptoken_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    ctx.accounts.ptoken_mint.key().as_ref(),
+                    ctx.accounts.signer.key().as_ref(),
+                    &[user_ptoken_vault_bump],
+                ]],
+            ),
+            ptoken_amount,
+        )?;
+        let listing = &mut ctx.accounts.listing;
+        listing.seller = ctx.accounts.signer.key();
+        listing.prob_pool = ctx.accounts.prob_pool.key();
+        listing.ptoken_amount = ptoken_amount;
+        listing.ask = ask;
+        listing.bid = 0;
+        listing.bidder = Pubkey::default();
+        emit!(events::Listed {
+            prob_pool: listing.prob_pool,
+            listing: listing.key(),
+            seller: listing.seller,
+            ptoken_amount,
+            ask,
+        });
+        Ok(())
+    }
+
+    pub fn update_ask(ctx: Context<UpdateAsk>, ask: u64) -> Result<()> {
+        require!(
+            ctx.accounts.listing.seller == ctx.accounts.signer.key(),
+            error::FortuneError::InvalidAskAuth
+        );
+        require!(ask > 0, error::FortuneError::ZeroAsk);
+        check_listing_unlocked(&ctx.accounts.seller_burn_request)?;
+        ctx.accounts.listing.ask = ask;
+        emit!(events::AskUpdated {
+            prob_pool: ctx.accounts.listing.prob_pool,
+            listing: ctx.accounts.listing.key(),
+            ask,
+        });
+        Ok(())
+    }
+
+    // Return escrowed ptokens to the seller's vault, refunding the best bid
+    pub fn cancel_listing(ctx: Context<CancelListing>) -> Result<()> {
+        require!(
+            ctx.accounts.listing.seller == ctx.accounts.signer.key(),
+            error::FortuneError::InvalidAskAuth
+        );
+        check_listing_unlocked(&ctx.accounts.seller_burn_request)?;
+        let listing = ctx.accounts.listing.key();
+        let escrow_bump = *ctx.bumps.get("listing_vault").unwrap();
+        let bid_bump = *ctx.bumps.get("bid_vault").unwrap();
+        let escrow_seeds: &[&[u8]] = &[&b"escrow"[..], listing.as_ref(), &[escrow_bump]];
+        let bid_seeds: &[&[u8]] = &[&b"bid"[..], listing.as_ref(), &[bid_bump]];
+        // Ptokens back to the seller
+        vault_transfer(
             ctx.accounts.token_program.to_account_info(),
-            anchor_spl::token::CloseAccount {
-                account: ctx.accounts.nft_vault.to_account_info(),
-                destination: ctx.accounts.signer.to_account_info(),
-                authority: ctx.accounts.nft_vault.to_account_info(),
-            },
-            &[&[
-                &b"vault"[..],
-                &ctx.accounts.nft_mint.key().as_ref(),
-                &ctx.accounts.prob_pool.key().as_ref(),
-                &[nft_vault_bump],
-            ]],
-        ))?;
+            ctx.accounts.listing_vault.to_account_info(),
+            ctx.accounts.user_ptoken_vault.to_account_info(),
+            escrow_seeds,
+            ctx.accounts.listing.ptoken_amount,
+        )?;
+        // Bid back to the bidder
+        if ctx.accounts.listing.bid > 0 {
+            vault_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.bid_vault.to_account_info(),
+                ctx.accounts.bid_refund.to_account_info(),
+                bid_seeds,
+                ctx.accounts.listing.bid,
+            )?;
+        }
+        vault_close(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_vault.to_account_info(),
+            ctx.accounts.signer.to_account_info(),
+            escrow_seeds,
+        )?;
+        vault_close(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.bid_vault.to_account_info(),
+            ctx.accounts.signer.to_account_info(),
+            bid_seeds,
+        )?;
+        emit!(events::ListingCancelled {
+            prob_pool: ctx.accounts.listing.prob_pool,
+            listing,
+            seller: ctx.accounts.signer.key(),
+            ptoken_amount: ctx.accounts.listing.ptoken_amount,
+        });
+        Ok(())
+    }
+
+    // Buy every ptoken of a listing at its ask, refunding the best bid
+    pub fn fill_listing(ctx: Context<FillListing>, max_lamports_in: u64) -> Result<()> {
+        ctx.accounts
+            .prob_pool
+            .check_market(Clock::get()?.unix_timestamp)?;
+        check_listing_unlocked(&ctx.accounts.seller_burn_request)?;
+        // Listings count toward the buyer's wallet limit
+        require!(
+            ctx.accounts.listing.ptoken_amount
+                <= ctx
+                    .accounts
+                    .prob_pool
+                    .limits
+                    .wallet_allowance(&ctx.accounts.user_stats),
+            error::FortuneError::WalletLimit
+        );
+        ctx.accounts.user_stats.record_bought(
+            ctx.accounts.prob_pool.key(),
+            ctx.accounts.signer.key(),
+            ctx.accounts.listing.ptoken_amount,
+        )?;
+        let ask = ctx.accounts.listing.ask;
+        // Seller may have raised the ask
+        require!(
+            ask <= max_lamports_in,
+            error::FortuneError::SlippageExceeded
+        );
+        let listing = ctx.accounts.listing.key();
+        let escrow_bump = *ctx.bumps.get("listing_vault").unwrap();
+        let bid_bump = *ctx.bumps.get("bid_vault").unwrap();
+        let escrow_seeds: &[&[u8]] = &[&b"escrow"[..], listing.as_ref(), &[escrow_bump]];
+        let bid_seeds: &[&[u8]] = &[&b"bid"[..], listing.as_ref(), &[bid_bump]];
+        // Pay the seller
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_quote_account.to_account_info(),
+                    to: ctx.accounts.seller_quote_account.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            ask,
+        )?;
+        // Ptokens to the buyer's vault
+        vault_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_vault.to_account_info(),
+            ctx.accounts.user_ptoken_vault.to_account_info(),
+            escrow_seeds,
+            ctx.accounts.listing.ptoken_amount,
+        )?;
+        // Bid back to the bidder
+        if ctx.accounts.listing.bid > 0 {
+            vault_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.bid_vault.to_account_info(),
+                ctx.accounts.bid_refund.to_account_info(),
+                bid_seeds,
+                ctx.accounts.listing.bid,
+            )?;
+        }
+        vault_close(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_vault.to_account_info(),
+            ctx.accounts.seller.to_account_info(),
+            escrow_seeds,
+        )?;
+        vault_close(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.bid_vault.to_account_info(),
+            ctx.accounts.seller.to_account_info(),
+            bid_seeds,
+        )?;
+        emit!(events::ListingFilled {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            listing,
+            seller: ctx.accounts.listing.seller,
+            buyer: ctx.accounts.signer.key(),
+            ptoken_amount: ctx.accounts.listing.ptoken_amount,
+            lamports: ask,
+        });
+        Ok(())
+    }
+
+    // Escrow a bid for a whole listing, it must beat the best bid which is refunded
+    pub fn place_bid(ctx: Context<PlaceBid>, bid: u64) -> Result<()> {
+        ctx.accounts
+            .prob_pool
+            .check_market(Clock::get()?.unix_timestamp)?;
+        check_listing_unlocked(&ctx.accounts.seller_burn_request)?;
+        require!(
+            bid > ctx.accounts.listing.bid,
+            error::FortuneError::BidTooLow
+        );
+        // Escrow the new bid
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_quote_account.to_account_info(),
+                    to: ctx.accounts.bid_vault.to_account_info(),
+                    authority: ctx.accounts.signer.to_account_info(),
+                },
+            ),
+            bid,
+        )?;
+        // Refund the bid it beats
+        if ctx.accounts.listing.bid > 0 {
+            let listing = ctx.accounts.listing.key();
+            let bid_bump = *ctx.bumps.get("bid_vault").unwrap();
+            vault_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.bid_vault.to_account_info(),
+                ctx.accounts.bid_refund.to_account_info(),
+                &[&b"bid"[..], listing.as_ref(), &[bid_bump]],
+                ctx.accounts.listing.bid,
+            )?;
+        }
+        ctx.accounts.listing.bid = bid;
+        ctx.accounts.listing.bidder = ctx.accounts.signer.key();
+        emit!(events::BidPlaced {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            listing: ctx.accounts.listing.key(),
+            bidder: ctx.accounts.signer.key(),
+            bid,
+        });
+        Ok(())
+    }
+
+    // Best bidder takes their bid back
+    pub fn withdraw_bid(ctx: Context<WithdrawBid>) -> Result<()> {
+        let bid = ctx.accounts.listing.bid;
+        let listing = ctx.accounts.listing.key();
+        let bid_bump = *ctx.bumps.get("bid_vault").unwrap();
+        vault_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.bid_vault.to_account_info(),
+            ctx.accounts.recipient.to_account_info(),
+            &[&b"bid"[..], listing.as_ref(), &[bid_bump]],
+            bid,
+        )?;
+        ctx.accounts.listing.bid = 0;
+        ctx.accounts.listing.bidder = Pubkey::default();
+        emit!(events::BidWithdrawn {
+            prob_pool: ctx.accounts.listing.prob_pool,
+            listing,
+            bidder: ctx.accounts.signer.key(),
+            bid,
+        });
+        Ok(())
+    }
+
+    // Seller sells the whole listing to the best bidder
+    pub fn accept_bid(ctx: Context<AcceptBid>, min_lamports_out: u64) -> Result<()> {
+        require!(
+            ctx.accounts.listing.seller == ctx.accounts.signer.key(),
+            error::FortuneError::InvalidAskAuth
+        );
+        ctx.accounts
+            .prob_pool
+            .check_market(Clock::get()?.unix_timestamp)?;
+        check_listing_unlocked(&ctx.accounts.seller_burn_request)?;
+        let bid = ctx.accounts.listing.bid;
+        require!(bid > 0, error::FortuneError::BidTooLow);
+        // Best bidder may have swapped their bid for a lower one
+        require!(
+            bid >= min_lamports_out,
+            error::FortuneError::SlippageExceeded
+        );
+        require!(
+            ctx.accounts.listing.ptoken_amount
+                <= ctx
+                    .accounts
+                    .prob_pool
+                    .limits
+                    .wallet_allowance(&ctx.accounts.bidder_stats),
+            error::FortuneError::WalletLimit
+        );
+        ctx.accounts.bidder_stats.record_bought(
+            ctx.accounts.prob_pool.key(),
+            ctx.accounts.bidder.key(),
+            ctx.accounts.listing.ptoken_amount,
+        )?;
+        let listing = ctx.accounts.listing.key();
+        let escrow_bump = *ctx.bumps.get("listing_vault").unwrap();
+        let bid_bump = *ctx.bumps.get("bid_vault").unwrap();
+        let escrow_seeds: &[&[u8]] = &[&b"escrow"[..], listing.as_ref(), &[escrow_bump]];
+        let bid_seeds: &[&[u8]] = &[&b"bid"[..], listing.as_ref(), &[bid_bump]];
+        // Bid to the seller
+        vault_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.bid_vault.to_account_info(),
+            ctx.accounts.recipient.to_account_info(),
+            bid_seeds,
+            bid,
+        )?;
+        // Ptokens to the bidder's vault
+        vault_transfer(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_vault.to_account_info(),
+            ctx.accounts.bidder_ptoken_vault.to_account_info(),
+            escrow_seeds,
+            ctx.accounts.listing.ptoken_amount,
+        )?;
+        vault_close(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.listing_vault.to_account_info(),
+            ctx.accounts.signer.to_account_info(),
+            escrow_seeds,
+        )?;
+        vault_close(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.bid_vault.to_account_info(),
+            ctx.accounts.signer.to_account_info(),
+            bid_seeds,
+        )?;
+        emit!(events::ListingFilled {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            listing,
+            seller: ctx.accounts.signer.key(),
+            buyer: ctx.accounts.listing.bidder,
+            ptoken_amount: ctx.accounts.listing.ptoken_amount,
+            lamports: bid,
+        });
+        Ok(())
+    }
+
+    // Burn ptokens in order to try to win the asset, callable by anyone once the
+    // request's randomness source can reveal
+    pub fn execute_burn(ctx: Context<ExecuteBurn>) -> Result<()> {
+        require!(
+            ctx.accounts.burn_request.status == BurnStatus::Pending,
+            error::FortuneError::NoBurnRequest
+        );
+        // Every prize already won
+        require!(
+            ctx.accounts.prob_pool.prizes_left(),
+            error::FortuneError::PoolClosed
+        );
+        // No prize is awarded below the reserve
+        require!(
+            ctx.accounts.prob_pool.reserve_met(),
+            error::FortuneError::ReserveNotMet
+        );
+        let burn_amount = ctx.accounts.burn_request.amount;
+        let seed = ctx.accounts.burn_request.seed;
+        let randomness = random::source(
+            ctx.accounts.burn_request.oracle,
+            ctx.accounts.burn_request.target_slot,
+            &ctx.accounts.slot_hashes,
+            &ctx.accounts.instructions,
+        )
+        .reveal(seed)?;
+
+        // Bump
+        let user_burn_bump = *ctx.bumps.get("user_burn").unwrap();
+        // Burn ptokens in user burn
+        token::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Burn {
+                    mint: ctx.accounts.ptoken_mint.to_account_info(),
+                    to: ctx.accounts.user_burn.to_account_info(),
+                    authority: ctx.accounts.user_burn.to_account_info(),
+                },
+                &[&[
+                    &b"burn"[..],
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    ctx.accounts.user.key().as_ref(),
+                    &[user_burn_bump],
+                ]],
+            ),
+            burn_amount,
+        )?;
+
+        // A stale request lost its randomness, it settles as a loss so the outcome
+        // cannot be chosen by waiting
+        let denominator = ctx.accounts.prob_pool.odds_denominator();
+        let mut draws = Vec::new();
+        let mut won = false;
+        let mut prize = Pubkey::default();
+        if let Some(randomness) = randomness {
+            match ctx.accounts.burn_request.mode {
+                BurnMode::Single => {
+                    let rng = random::expand_with_pubkey(randomness, seed) % denominator;
+                    // P(win) = P(X < burn_amount) = 1-P(X >= burn_amount)
+                    won = rng >= denominator.saturating_sub(burn_amount);
+                    draws.push(rng);
+                }
+                BurnMode::MultiTicket => {
+                    let (ticket_draws, ticket_won) =
+                        random::draw_tickets(randomness, seed, burn_amount, denominator);
+                    draws = ticket_draws;
+                    won = ticket_won;
+                }
+            }
+            if won {
+                // Winner can claim one of the open prizes
+                let prob_pool = &mut ctx.accounts.prob_pool;
+                let index = prob_pool.pick_prize(random::prize_roll(randomness, seed));
+                prob_pool.prizes[index].status = PrizeStatus::Won;
+                prob_pool.prizes[index].winner = ctx.accounts.user.key();
+                prize = prob_pool.prizes[index].mint;
+            }
+        }
+        emit!(events::BurnExecuted {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.user.key(),
+            ptoken_amount: burn_amount,
+            denominator,
+            mode: ctx.accounts.burn_request.mode,
+            draws: draws.clone(),
+            won,
+            prize,
+            expired: randomness.is_none(),
+        });
+        // Update prob pool data
+        ctx.accounts.prob_pool.outstanding_ptokens = ctx
+            .accounts
+            .prob_pool
+            .outstanding_ptokens
+            .checked_sub(burn_amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        ctx.accounts.prob_pool.pending_burns -= 1;
+        // Receipt of every draw for auditing
+        let burn_request = &mut ctx.accounts.burn_request;
+        burn_request.status = BurnStatus::Settled;
+        burn_request.draws = draws;
+        burn_request.won = won;
         Ok(())
     }
+
+    // Return ptokens of a pending burn request to the user. Slot hash requests can
+    // always be settled by anyone, so only oracle requests time out, otherwise a
+    // user could cancel once the revealed slot hash shows a loss. Anyone can cancel
+    // so a dead oracle or a won out pool never blocks refunds or closing
+    pub fn cancel_burn(ctx: Context<CancelBurn>) -> Result<()> {
+        require!(
+            ctx.accounts.burn_request.status == BurnStatus::Pending,
+            error::FortuneError::NoBurnRequest
+        );
+        let pool_closed = !ctx.accounts.prob_pool.prizes_left();
+        let timed_out = ctx.accounts.burn_request.oracle != Pubkey::default()
+            && Clock::get()?.slot > ctx.accounts.burn_request.target_slot + BURN_TIMEOUT_SLOTS;
+        require!(
+            pool_closed || timed_out,
+            error::FortuneError::BurnNotCancellable
+        );
+        let burn_amount = ctx.accounts.burn_request.amount;
+        // Bumps
+        let user_burn_bump = *ctx.bumps.get("user_burn").unwrap();
+        let vault_bump = *ctx.bumps.get("fortune_lamport_vault").unwrap();
+        // Transfer from user burn back to user vault
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.user_burn.to_account_info(),
+                    to: ctx.accounts.user_ptoken_vault.to_account_info(),
+                    authority: ctx.accounts.user_burn.to_account_info(),
+                },
+                &[&[
+                    &b"burn"[..],
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    ctx.accounts.user.key().as_ref(),
+                    &[user_burn_bump],
+                ]],
+            ),
+            burn_amount,
+        )?;
+        // Refund burn cost when every prize was won under the request, a timed out
+        // request keeps it with the protocol and the creator
+        let (refund, creator_refund) = if pool_closed {
+            let creator_fee = ctx.accounts.burn_request.creator_fee;
+            (
+                (ctx.accounts.burn_request.fee_paid - creator_fee)
+                    .min(ctx.accounts.fortune_lamport_vault.amount),
+                // Creator may have withdrawn it already
+                creator_fee.min(ctx.accounts.creator_sol_vault.amount),
+            )
+        } else {
+            (0, 0)
+        };
+        if creator_refund > 0 {
+            let creator_vault_bump = *ctx.bumps.get("creator_sol_vault").unwrap();
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: ctx.accounts.creator_sol_vault.to_account_info(),
+                        to: ctx.accounts.recipient.to_account_info(),
+                        authority: ctx.accounts.creator_sol_vault.to_account_info(),
+                    },
+                    &[&[
+                        &b"creator"[..],
+                        ctx.accounts.native_mint.key().as_ref(),
+                        ctx.accounts.prob_pool.key().as_ref(),
+                        ctx.accounts.prob_pool.authority.as_ref(),
+                        &[creator_vault_bump],
+                    ]],
+                ),
+                creator_refund,
+            )?;
+        }
+        if refund > 0 {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Transfer {
+                        from: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                        to: ctx.accounts.recipient.to_account_info(),
+                        authority: ctx.accounts.fortune_lamport_vault.to_account_info(),
+                    },
+                    &[&[
+                        &b"vault"[..],
+                        ctx.accounts.native_mint.key().as_ref(),
+                        &[vault_bump],
+                    ]],
+                ),
+                refund,
+            )?;
+            // Refunded fees were never earned
+            ctx.accounts.quote.fees_collected = ctx
+                .accounts
+                .quote
+                .fees_collected
+                .checked_sub(refund)
+                .ok_or(error::FortuneError::MathOverflow)?;
+        }
+        // Update prob pool data
+        ctx.accounts.prob_pool.pending_burns -= 1;
+        ctx.accounts.burn_request.status = BurnStatus::Cancelled;
+        emit!(events::BurnCancelled {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.user.key(),
+            ptoken_amount: burn_amount,
+            refund: refund + creator_refund,
+        });
+        Ok(())
+    }
+
+    // Commit a raffle to a future randomness reveal once it sold out or closed,
+    // callable by anyone
+    pub fn request_draw(ctx: Context<RequestDraw>) -> Result<()> {
+        require!(
+            ctx.accounts.prob_pool.mode == PoolMode::Raffle,
+            error::FortuneError::WrongPoolMode
+        );
+        require!(
+            ctx.accounts.prob_pool.prizes_left() && !ctx.accounts.prob_pool.refunding,
+            error::FortuneError::PoolClosed
+        );
+        require!(
+            ctx.accounts.prob_pool.tickets_sold() > 0,
+            error::FortuneError::NoTickets
+        );
+        require!(
+            ctx.accounts.prob_pool.ptoken_supply == 0
+                || Clock::get()?.unix_timestamp >= ctx.accounts.prob_pool.open_until,
+            error::FortuneError::RaffleOpen
+        );
+        // A draw the oracle never settled can be committed again. Slot hash draws
+        // cannot, their outcome is public once the target slot passes.
+        let slot = Clock::get()?.slot;
+        let pending = ctx.accounts.draw.status == BurnStatus::Pending;
+        require!(
+            !pending
+                || (ctx.accounts.draw.oracle != Pubkey::default()
+                    && slot > ctx.accounts.draw.target_slot + BURN_TIMEOUT_SLOTS),
+            error::FortuneError::BurnPending
+        );
+        let burn_nonce = ctx.accounts.prob_pool.burn_nonce;
+        let draw = &mut ctx.accounts.draw;
+        draw.prob_pool = ctx.accounts.prob_pool.key();
+        draw.status = BurnStatus::Pending;
+        draw.target_slot = slot + BURN_SLOT_DELAY;
+        draw.oracle = ctx.accounts.state.oracle;
+        draw.seed = keccak::hashv(&[
+            ctx.accounts.prob_pool.key().as_ref(),
+            &b"draw"[..],
+            &burn_nonce.to_le_bytes(),
+        ])
+        .to_bytes();
+        ctx.accounts.prob_pool.burn_nonce += 1;
+        // A pending draw holds off refunds like a pending burn
+        if !pending {
+            ctx.accounts.prob_pool.pending_burns += 1;
+        }
+        emit!(events::DrawRequested {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            tickets_sold: ctx.accounts.prob_pool.tickets_sold(),
+            target_slot: ctx.accounts.draw.target_slot,
+            oracle: ctx.accounts.draw.oracle,
+            seed: ctx.accounts.draw.seed,
+        });
+        Ok(())
+    }
+
+    // Draw the winning raffle ticket, the entry holding it is computed off chain
+    // from the revealed randomness. The entry's buyer can then claim the asset.
+    pub fn execute_draw(ctx: Context<ExecuteDraw>) -> Result<()> {
+        require!(
+            ctx.accounts.draw.status == BurnStatus::Pending,
+            error::FortuneError::NoDraw
+        );
+        let seed = ctx.accounts.draw.seed;
+        let randomness = random::source(
+            ctx.accounts.draw.oracle,
+            ctx.accounts.draw.target_slot,
+            &ctx.accounts.slot_hashes,
+            &ctx.accounts.instructions,
+        )
+        .reveal(seed)?;
+        let tickets_sold = ctx.accounts.prob_pool.tickets_sold();
+        let mut ticket = 0;
+        let mut winner = Pubkey::default();
+        if let Some(randomness) = randomness {
+            ticket = random::expand_with_pubkey(randomness, seed) % tickets_sold;
+            require!(
+                ctx.accounts.entry.holds(ticket),
+                error::FortuneError::WrongEntry
+            );
+            winner = ctx.accounts.entry.user;
+            // Raffles hold a single prize
+            ctx.accounts.prob_pool.prizes[0].status = PrizeStatus::Won;
+            ctx.accounts.prob_pool.prizes[0].winner = winner;
+            ctx.accounts.draw.status = BurnStatus::Settled;
+            ctx.accounts.draw.ticket = ticket;
+            ctx.accounts.draw.winner = winner;
+        } else {
+            // Slot hash expired, the draw has to be requested again
+            ctx.accounts.draw.status = BurnStatus::Cancelled;
+        }
+        ctx.accounts.prob_pool.pending_burns -= 1;
+        emit!(events::RaffleDrawn {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            tickets_sold,
+            ticket,
+            winner,
+            expired: randomness.is_none(),
+        });
+        Ok(())
+    }
+
+    // Claim a prize won by the signer
+    pub fn claim_asset(ctx: Context<ClaimAsset>) -> Result<()> {
+        // Bump
+        let nft_vault_bump = *ctx.bumps.get("nft_vault").unwrap();
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            !ctx.accounts.prob_pool.claim_expired(now),
+            error::FortuneError::ClaimExpired
+        );
+        // Creator cannot claim
+        let nft_mint = ctx.accounts.nft_mint.key();
+        let signer = ctx.accounts.signer.key();
+        let prize = ctx
+            .accounts
+            .prob_pool
+            .prizes
+            .iter_mut()
+            .find(|prize| prize.mint == nft_mint)
+            .ok_or(error::FortuneError::NoClaim)?;
+        require!(
+            prize.status == PrizeStatus::Won && prize.winner == signer,
+            error::FortuneError::NoClaim
+        );
+        // SOL bounties are claimed as lamports
+        require!(
+            prize.kind != PrizeKind::Sol,
+            error::FortuneError::InvalidPrize
+        );
+        prize.status = PrizeStatus::Claimed;
+        let amount = prize.amount;
+        // Transfer prize to claimer
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    to: ctx.accounts.nft_account.to_account_info(),
+                    authority: ctx.accounts.nft_vault.to_account_info(),
+                },
+                &[&[
+                    &b"prize"[..],
+                    &ctx.accounts.nft_mint.key().as_ref(),
+                    &ctx.accounts.prob_pool.key().as_ref(),
+                    &[nft_vault_bump],
+                ]],
+            ),
+            amount,
+        )?;
+        emit!(events::AssetClaimed {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.signer.key(),
+            nft_mint: ctx.accounts.nft_mint.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    // Claim a SOL bounty won by the signer, paid in lamports
+    pub fn claim_sol(ctx: Context<ClaimSol>) -> Result<()> {
+        // Bump
+        let prize_vault_bump = *ctx.bumps.get("prize_vault").unwrap();
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            !ctx.accounts.prob_pool.claim_expired(now),
+            error::FortuneError::ClaimExpired
+        );
+        let signer = ctx.accounts.signer.key();
+        let prize = ctx
+            .accounts
+            .prob_pool
+            .prizes
+            .iter_mut()
+            .find(|prize| prize.kind == PrizeKind::Sol)
+            .ok_or(error::FortuneError::NoClaim)?;
+        require!(
+            prize.status == PrizeStatus::Won && prize.winner == signer,
+            error::FortuneError::NoClaim
+        );
+        prize.status = PrizeStatus::Claimed;
+        let amount = prize.amount;
+        // Unwrap into the pool account, its rent goes to the creator when the pool closes
+        vault_close(
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.prize_vault.to_account_info(),
+            ctx.accounts.prob_pool.to_account_info(),
+            &[
+                &b"prize"[..],
+                ctx.accounts.native_mint.key().as_ref(),
+                ctx.accounts.prob_pool.key().as_ref(),
+                &[prize_vault_bump],
+            ],
+        )?;
+        **ctx
+            .accounts
+            .prob_pool
+            .to_account_info()
+            .try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.signer.try_borrow_mut_lamports()? += amount;
+        emit!(events::AssetClaimed {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: signer,
+            nft_mint: ctx.accounts.native_mint.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    // Past the refund deadline, return every prize nobody can claim to the creator
+    // and let holders redeem ptokens pro-rata against the pool lamport vault
+    pub fn start_refund<'info>(ctx: Context<'_, '_, '_, 'info, StartRefund<'info>>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let prob_pool = &ctx.accounts.prob_pool;
+        require!(
+            !prob_pool.refunding && prob_pool.prizes.iter().any(|prize| prize.unsettled()),
+            error::FortuneError::PoolClosed
+        );
+        require!(
+            prob_pool
+                .prizes
+                .iter()
+                .any(|prize| prob_pool.returnable(prize, now)),
+            error::FortuneError::ActiveClaim
+        );
+        // Pools that closed short of their reserve refund right away
+        require!(
+            now >= prob_pool.refund_deadline
+                || (now >= prob_pool.open_until && !prob_pool.reserve_met()),
+            error::FortuneError::RefundDeadline
+        );
+        require!(
+            prob_pool.pending_burns == 0,
+            error::FortuneError::BurnPending
+        );
+        // Transfer NFTs back to creator
+        let vaults = prize_vaults(
+            &ctx.accounts.prob_pool,
+            ctx.accounts.nft_vault.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+        return_prizes(
+            &mut ctx.accounts.prob_pool,
+            &vaults,
+            &ctx.accounts.token_program.to_account_info(),
+            now,
+        )?;
+        // Liquidity providers keep their part of the curve, redeemed from the LP vault
+        let lp_claim = ctx
+            .accounts
+            .prob_pool
+            .lp_claim(ctx.accounts.pool_lamport_vault.amount)?;
+        if lp_claim > 0 {
+            let lamport_vault_bump = *ctx.bumps.get("pool_lamport_vault").unwrap();
+            vault_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.pool_lamport_vault.to_account_info(),
+                ctx.accounts.lp_vault.to_account_info(),
+                &[
+                    &b"vault"[..],
+                    ctx.accounts.quote_mint.key().as_ref(),
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    &[lamport_vault_bump],
+                ],
+                lp_claim,
+            )?;
+        }
+        // Snapshot the refund pot so every holder gets the same rate
+        let prob_pool = &mut ctx.accounts.prob_pool;
+        prob_pool.refunding = true;
+        prob_pool.refund_lamports = ctx.accounts.pool_lamport_vault.amount - lp_claim;
+        prob_pool.refund_ptokens = prob_pool.outstanding_ptokens;
+        prob_pool.redeem_deadline = now
+            .checked_add(REDEEM_WINDOW)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        emit!(events::RefundStarted {
+            prob_pool: prob_pool.key(),
+            refund_lamports: prob_pool.refund_lamports,
+            refund_ptokens: prob_pool.refund_ptokens,
+            redeem_deadline: prob_pool.redeem_deadline,
+        });
+        Ok(())
+    }
+
+    // Burn ptokens for a pro-rata share of the refund pot, from the signer's vault or
+    // any ptoken account they withdrew to
+    pub fn redeem(ctx: Context<Redeem>, ptoken_amount: u64) -> Result<()> {
+        require!(
+            ctx.accounts.prob_pool.refunding,
+            error::FortuneError::NotRefunding
+        );
+        let lamports = amm::pro_rata(
+            ptoken_amount,
+            ctx.accounts.prob_pool.refund_lamports,
+            ctx.accounts.prob_pool.refund_ptokens,
+        )?;
+        // Bumps
+        let lamport_vault_bump = *ctx.bumps.get("pool_lamport_vault").unwrap();
+        // Burn ptokens in the signer's account
+        if ctx.accounts.ptoken_account.owner == ctx.accounts.signer.key() {
+            token::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Burn {
+                        mint: ctx.accounts.ptoken_mint.to_account_info(),
+                        to: ctx.accounts.ptoken_account.to_account_info(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                ),
+                ptoken_amount,
+            )?;
+        } else {
+            // Otherwise it has to be the signer's vault with the protocol
+            let (user_ptoken_vault, user_ptoken_vault_bump) = Pubkey::find_program_address(
+                &[
+                    &b"vault"[..],
+                    ctx.accounts.ptoken_mint.key().as_ref(),
+                    ctx.accounts.signer.key().as_ref(),
+                ],
+                ctx.program_id,
+            );
+            require!(
+                ctx.accounts.ptoken_account.key() == user_ptoken_vault,
+                error::FortuneError::WrongPtokenAccount
+            );
+            token::burn(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_spl::token::Burn {
+                        mint: ctx.accounts.ptoken_mint.to_account_info(),
+                        to: ctx.accounts.ptoken_account.to_account_info(),
+                        authority: ctx.accounts.ptoken_account.to_account_info(),
+                    },
+                    &[&[
+                        &b"vault"[..],
+                        ctx.accounts.ptoken_mint.key().as_ref(),
+                        ctx.accounts.signer.key().as_ref(),
+                        &[user_ptoken_vault_bump],
+                    ]],
+                ),
+                ptoken_amount,
+            )?;
+        }
+        // Transfer refund to holder
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.pool_lamport_vault.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                    authority: ctx.accounts.pool_lamport_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    ctx.accounts.quote_mint.key().as_ref(),
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    &[lamport_vault_bump],
+                ]],
+            ),
+            lamports,
+        )?;
+        // Update prob pool data
+        ctx.accounts.prob_pool.outstanding_ptokens = ctx
+            .accounts
+            .prob_pool
+            .outstanding_ptokens
+            .checked_sub(ptoken_amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        emit!(events::Redeemed {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            user: ctx.accounts.signer.key(),
+            ptoken_amount,
+            lamports,
+        });
+        Ok(())
+    }
+
+    // Close a probability pool, requires no outstanding ptokens, a drawn raffle or the
+    // redemption window of a refund to have passed
+    pub fn close_pool<'info>(ctx: Context<'_, '_, '_, 'info, ClosePool<'info>>) -> Result<()> {
+        // Bumps
+        let lamport_vault_bump = *ctx.bumps.get("pool_lamport_vault").unwrap();
+        let ptoken_vault_bump = *ctx.bumps.get("pool_ptoken_vault").unwrap();
+
+        // No outstanding ptokens, raffle tickets are spent once the draw picked a winner.
+        // Refund pots not redeemed in time are swept to the creator.
+        let now = Clock::get()?.unix_timestamp;
+        let drawn = ctx.accounts.prob_pool.prizes[0].status != PrizeStatus::Open;
+        let redeem_closed =
+            ctx.accounts.prob_pool.refunding && now >= ctx.accounts.prob_pool.redeem_deadline;
+        require!(
+            ctx.accounts.prob_pool.outstanding_ptokens == 0
+                || (ctx.accounts.prob_pool.mode == PoolMode::Raffle && drawn)
+                || redeem_closed,
+            error::FortuneError::OutstandingProb
+        );
+        // No active claim outstanding, an unclaimed win is forfeited after the deadline
+        require!(
+            !ctx.accounts.prob_pool.active_claim(now),
+            error::FortuneError::ActiveClaim
+        );
+        // Transfer NFTs never won and not already returned by a refund back to creator
+        let vaults = prize_vaults(
+            &ctx.accounts.prob_pool,
+            ctx.accounts.nft_vault.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+        return_prizes(
+            &mut ctx.accounts.prob_pool,
+            &vaults,
+            &ctx.accounts.token_program.to_account_info(),
+            now,
+        )?;
+        // Liquidity providers' part of the curve to the LP vault, a refund already moved it
+        let lp_claim = if ctx.accounts.prob_pool.refunding {
+            0
+        } else {
+            ctx.accounts
+                .prob_pool
+                .lp_claim(ctx.accounts.pool_lamport_vault.amount)?
+        };
+        if lp_claim > 0 {
+            vault_transfer(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.pool_lamport_vault.to_account_info(),
+                ctx.accounts.lp_vault.to_account_info(),
+                &[
+                    &b"vault"[..],
+                    ctx.accounts.quote_mint.key().as_ref(),
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    &[lamport_vault_bump],
+                ],
+                lp_claim,
+            )?;
+        }
+        // Transfer pool lamport funds to recipient
+        let pool_lamports = ctx.accounts.pool_lamport_vault.amount - lp_claim;
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Transfer {
+                    from: ctx.accounts.pool_lamport_vault.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                    authority: ctx.accounts.pool_lamport_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    &ctx.accounts.quote_mint.key().as_ref(),
+                    &ctx.accounts.prob_pool.key().as_ref(),
+                    &[lamport_vault_bump],
+                ]],
+            ),
+            pool_lamports,
+        )?;
+        // Burn all ptokens
+        token::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::Burn {
+                    mint: ctx.accounts.ptoken_mint.to_account_info(),
+                    to: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                    authority: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                },
+                &[&[
+                    &b"vault"[..],
+                    &ctx.accounts.ptoken_mint.key().as_ref(),
+                    &ctx.accounts.prob_pool.key().as_ref(),
+                    &[ptoken_vault_bump],
+                ]],
+            ),
+            ctx.accounts.pool_ptoken_vault.amount,
+        )?;
+        // Close pool ptoken vault
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_spl::token::CloseAccount {
+                account: ctx.accounts.pool_ptoken_vault.to_account_info(),
+                destination: ctx.accounts.signer.to_account_info(),
+                authority: ctx.accounts.pool_ptoken_vault.to_account_info(),
+            },
+            &[&[
+                &b"vault"[..],
+                &ctx.accounts.ptoken_mint.key().as_ref(),
+                &ctx.accounts.prob_pool.key().as_ref(),
+                &[ptoken_vault_bump],
+            ]],
+        ))?;
+        // Close pool lamport vault
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_spl::token::CloseAccount {
+                account: ctx.accounts.pool_lamport_vault.to_account_info(),
+                destination: ctx.accounts.signer.to_account_info(),
+                authority: ctx.accounts.pool_lamport_vault.to_account_info(),
+            },
+            &[&[
+                &b"vault"[..],
+                &ctx.accounts.quote_mint.key().as_ref(),
+                &ctx.accounts.prob_pool.key().as_ref(),
+                &[lamport_vault_bump],
+            ]],
+        ))?;
+        // LP vault is only kept while providers have something to redeem
+        if ctx.accounts.prob_pool.lp_shares == 0 && ctx.accounts.lp_vault.amount == 0 {
+            let lp_vault_bump = *ctx.bumps.get("lp_vault").unwrap();
+            vault_close(
+                ctx.accounts.token_program.to_account_info(),
+                ctx.accounts.lp_vault.to_account_info(),
+                ctx.accounts.signer.to_account_info(),
+                &[
+                    &b"lp_vault"[..],
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    &[lp_vault_bump],
+                ],
+            )?;
+        }
+        // Close pool nft vaults, SOL bounty vaults were closed when claimed or returned
+        for (prize, (vault, _, bump)) in ctx.accounts.prob_pool.prizes.iter().zip(&vaults) {
+            if prize.kind == PrizeKind::Sol {
+                continue;
+            }
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_spl::token::CloseAccount {
+                    account: vault.clone(),
+                    destination: ctx.accounts.signer.to_account_info(),
+                    authority: vault.clone(),
+                },
+                &[&[
+                    &b"prize"[..],
+                    prize.mint.as_ref(),
+                    ctx.accounts.prob_pool.key().as_ref(),
+                    &[*bump],
+                ]],
+            ))?;
+        }
+        emit!(events::PoolClosed {
+            prob_pool: ctx.accounts.prob_pool.key(),
+            authority: ctx.accounts.signer.key(),
+            lamports: pool_lamports,
+            prizes_claimed: ctx
+                .accounts
+                .prob_pool
+                .prizes
+                .iter()
+                .filter(|prize| prize.status == PrizeStatus::Claimed)
+                .count() as u8,
+        });
+        Ok(())
+    }
+}
+
+/*
+- signer: Any, becomes the authority of the program
+- spl_vault: Initial program vault for SOL
+- spl_mint: Native mint
+- state: State
+- quote: SOL quote mint registration
+- collections: Collection allowlist
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        init,
+        payer = signer,
+        token::mint = spl_mint,
+        token::authority = spl_vault,
+        seeds = [b"vault", spl_mint.key().as_ref()],
+        bump
+    )]
+    pub spl_vault: Box<Account<'info, TokenAccount>>,
+    #[account(address = spl_token::native_mint::ID)]
+    pub spl_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        space = 250,
+        payer = signer,
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        init,
+        space = Quote::LEN,
+        payer = signer,
+        seeds = [b"quote", spl_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        init,
+        space = 600,
+        payer = signer,
+        seeds = [b"collections"],
+        bump
+    )]
+    pub collections: Box<Account<'info, Collections>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- authority: Fortune authority
+- state: State
+*/
+#[derive(Accounts)]
+pub struct UpdateState<'info> {
+    #[account(constraint = authority.key() == state.authority)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+/*
+- authority: Fortune authority
+- state: State
+- collections: Collection allowlist
+*/
+#[derive(Accounts)]
+pub struct SetCollections<'info> {
+    #[account(constraint = authority.key() == state.authority)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [b"collections"],
+        bump
+    )]
+    pub collections: Box<Account<'info, Collections>>,
+}
+
+/*
+- new_authority: Pending authority proposed by the current one
+- state: State
+*/
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(constraint = new_authority.key() == state.pending_authority)]
+    pub new_authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+/*
+- authority: Fortune authority, pays for the new accounts
+- fortune_vault: Protocol fee vault for the quote mint
+- quote_mint: SPL mint pools may be priced in
+- quote: Quote mint registration and fee accounting
+- state: State
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct AddQuoteMint<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == state.authority
+    )]
+    pub authority: Signer<'info>,
+    // Fee vault may already hold tokens sent to its address
+    #[account(
+        init_if_needed,
+        payer = authority,
+        token::mint = quote_mint,
+        token::authority = fortune_vault,
+        seeds = [b"vault", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub fortune_vault: Box<Account<'info, TokenAccount>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        space = Quote::LEN,
+        payer = authority,
+        seeds = [b"quote", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- authority: Fortune authority
+- quote_mint: Registered quote mint
+- quote: Quote mint registration to update
+- state: State
+*/
+#[derive(Accounts)]
+pub struct UpdateQuote<'info> {
+    #[account(constraint = authority.key() == state.authority)]
+    pub authority: Signer<'info>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"quote", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+/*
+- authority: Fortune authority
+- recipient: Token account to send fees to
+- fortune_lamport_vault: Protocol's fee vault for the quote mint
+- quote_mint: Mint of the fees to withdraw
+- quote: Quote mint fee accounting
+- state: State
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(constraint = authority.key() == state.authority)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = recipient.mint == quote_mint.key()
+    )]
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"quote", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- authority: Fortune authority, pays the temporary vault rent
+- recipient: System account to send SOL to
+- unwrap_vault: Temporary vault, closed within the instruction
+- fortune_lamport_vault: Protocol's lamport vault
+- native_mint: NATIVE_MINT
+- quote: SOL fee accounting
+- state: State
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct UnwrapFees<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == state.authority
+    )]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    #[account(
+        init,
+        payer = authority,
+        token::mint = native_mint,
+        token::authority = unwrap_vault,
+        seeds = [b"unwrap", native_mint.key().as_ref()],
+        bump
+    )]
+    pub unwrap_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", native_mint.key().as_ref()],
+        bump
+    )]
+    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    #[account(address = spl_token::native_mint::ID)]
+    pub native_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"quote", native_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Any, pays SOL bounties
+- nft_account: TokenAccount with the prize, a wSOL account for SOL bounties
+- prob_pool: ProbPool
+- pool_metadata: Name, URI and category of the pool
+- ptoken_mint: Mint for ProbPool
+- nft_vault: Pool vault for the prize
+- lamport_vault: Pool vault for the quote mint
+- ptoken_vault: Pool vault for ptokens
+- creator_vault: Creator's fee vault for the quote mint
+- creator_sol_vault: Creator's fee vault for burn costs
+- lp_mint: LP share mint for the pool
+- lp_vault: Liquidity providers' vault for the quote mint
+- nft_mint: Mint for the prize stored in the pool, native mint for SOL bounties
+- nft_metadata: Metaplex metadata of the prize mint
+- collections: Collection allowlist
+- quote_mint: Mint the pool is priced in
+- native_mint: NATIVE MINT
+- quote: Registration of the quote mint
+- state: State
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct CreatePool<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    // Holds the NFT, owned by the signer
+    #[account(
+        mut,
+        constraint = nft_account.owner == signer.key(),
+        constraint = nft_account.mint == nft_mint.key(),
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    // Prob pools are generated from a keypair
+    #[account(
+        init,
+        space = ProbPool::LEN,
+        payer = signer
+    )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        init,
+        space = PoolMetadata::LEN,
+        payer = signer,
+        seeds = [b"metadata", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_metadata: Box<Account<'info, PoolMetadata>>,
+    // Ptoken mint is unique for each pool
+    #[account(
+        init,
+        payer = signer,
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = ptoken_mint
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    // Vault for nft
+    #[account(
+        init,
+        payer = signer,
+        token::mint = nft_mint,
+        token::authority = nft_vault,
+        seeds = [b"prize", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
+    // Vault for SPL token
+    #[account(
+        init,
+        payer = signer,
+        token::mint = quote_mint,
+        token::authority = lamport_vault,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lamport_vault: Box<Account<'info, TokenAccount>>,
+    // Vault for ptokens
+    #[account(
+        init,
+        payer = signer,
+        token::mint = ptoken_mint,
+        token::authority = ptoken_vault,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub ptoken_vault: Box<Account<'info, TokenAccount>>,
+    // Pool creator's share of the swap fees
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = quote_mint,
+        token::authority = creator_vault,
+        seeds = [
+            b"creator",
+            quote_mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            signer.key().as_ref()
+        ],
+        bump
+    )]
+    pub creator_vault: Box<Account<'info, TokenAccount>>,
+    // Pool creator's share of the burn costs, the same vault for SOL pools
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = native_mint,
+        token::authority = creator_sol_vault,
+        seeds = [
+            b"creator",
+            native_mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            signer.key().as_ref()
+        ],
+        bump
+    )]
+    pub creator_sol_vault: Box<Account<'info, TokenAccount>>,
+    // LP shares of third party liquidity
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"lp", prob_pool.key().as_ref()],
+        bump,
+        mint::decimals = quote_mint.decimals,
+        mint::authority = lp_mint
+    )]
+    pub lp_mint: Box<Account<'info, Mint>>,
+    // Liquidity providers' fees, and their claim once the pool refunds or closes
+    #[account(
+        init,
+        payer = signer,
+        token::mint = quote_mint,
+        token::authority = lp_vault,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+    // Mint address identifies the NFT
+    #[account()]
+    pub nft_mint: Box<Account<'info, Mint>>,
+    /// CHECK: Metadata PDA of the nft mint, read only while collections are gated
+    pub nft_metadata: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"collections"],
+        bump
+    )]
+    pub collections: Box<Account<'info, Collections>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(address = spl_token::native_mint::ID)]
+    pub native_mint: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [b"quote", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- authority: Pool authority
+- prob_pool: Probability pool
+- pool_metadata: Name, URI and category of the pool
+- state: State
+*/
+#[derive(Accounts)]
+pub struct UpdatePoolMetadata<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = prob_pool.authority == authority.key()
+    )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        mut,
+        seeds = [b"metadata", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_metadata: Box<Account<'info, PoolMetadata>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+/*
+- authority: Pool authority
+- recipient: Token account to send the fees to
+- creator_vault: Creator's fee vault for the mint
+- prob_pool: Probability pool, may already be closed
+- mint: Quote mint, or native mint for burn costs
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct WithdrawCreatorFees<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = recipient.mint == mint.key()
+    )]
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    // Seeds bind the vault to the pool authority
+    #[account(
+        mut,
+        seeds = [
+            b"creator",
+            mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            authority.key().as_ref()
+        ],
+        bump
+    )]
+    pub creator_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Only part of the vault seeds
+    pub prob_pool: UncheckedAccount<'info>,
+    pub mint: Box<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- signer: Pool creator, pays SOL bounties
+- nft_account: Signer's token account holding the prize, a wSOL account for SOL bounties
+- prob_pool: Probability pool
+- nft_vault: Pool vault for the new prize
+- nft_mint: Mint of the new prize, native mint for SOL bounties
+- nft_metadata: Metaplex metadata of the prize mint
+- collections: Collection allowlist
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct AddPrize<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = nft_account.owner == signer.key(),
+        constraint = nft_account.mint == nft_mint.key(),
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = prob_pool.authority == signer.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    // Vault for nft
+    #[account(
+        init,
+        payer = signer,
+        token::mint = nft_mint,
+        token::authority = nft_vault,
+        seeds = [b"prize", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
+    pub nft_mint: Box<Account<'info, Mint>>,
+    /// CHECK: Metadata PDA of the nft mint, read only while collections are gated
+    pub nft_metadata: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"collections"],
+        bump
+    )]
+    pub collections: Box<Account<'info, Collections>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Liquidity provider
+- user_quote_account: Provider's token account to pay from
+- lp_account: Provider's token account for LP shares
+- pool_lamport_vault: Pool's lamport vault
+- pool_ptoken_vault: Pool's ptoken vault
+- lp_vault: Liquidity providers' fee vault
+- prob_pool: Probability pool to add liquidity to
+- ptoken_mint: Ptoken mint for prob pool
+- lp_mint: LP share mint for prob pool
+- quote_mint: Mint the pool is priced in
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_quote_account.mint == quote_mint.key()
+    )]
+    pub user_quote_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = lp_account.mint == lp_mint.key()
+    )]
+    pub lp_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = prob_pool.lamport_vault == pool_lamport_vault.key(),
+        constraint = prob_pool.ptoken_vault == pool_ptoken_vault.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        mut,
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lp", prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub lp_mint: Box<Account<'info, Mint>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- signer: Liquidity provider
+- lp_account: Provider's token account holding LP shares
+- recipient: Quote token account to give liquidity and fees to
+- pool_lamport_vault: Pool's lamport vault
+- pool_ptoken_vault: Pool's ptoken vault
+- lp_vault: Liquidity providers' fee vault
+- prob_pool: Probability pool to remove liquidity from
+- ptoken_mint: Ptoken mint for prob pool
+- lp_mint: LP share mint for prob pool
+- quote_mint: Mint the pool is priced in
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct RemoveLiquidity<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = lp_account.mint == lp_mint.key()
+    )]
+    pub lp_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = recipient.mint == quote_mint.key()
+    )]
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = prob_pool.lamport_vault == pool_lamport_vault.key(),
+        constraint = prob_pool.ptoken_vault == pool_ptoken_vault.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        mut,
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"lp", prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub lp_mint: Box<Account<'info, Mint>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- signer: Liquidity provider
+- lp_account: Provider's token account holding LP shares
+- recipient: Quote token account to give the redemption to
+- lp_vault: Liquidity providers' vault
+- lp_mint: LP share mint for prob pool
+- prob_pool: Refunding probability pool, may already be closed
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct RedeemLp<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = lp_account.mint == lp_mint.key()
+    )]
+    pub lp_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = recipient.mint == lp_vault.mint
+    )]
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"lp", prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub lp_mint: Box<Account<'info, Mint>>,
+    /// CHECK: Only part of the vault and mint seeds, checked to be refunding while open
+    pub prob_pool: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- signer: Any
+- user_quote_account: Buyer's token account to pay from
+- pool_lamport_vault: Pool's lamport vault
+- pool_ptoken_vault: Pool's ptoken vault
+- prob_pool: Probability pool to buy from
+- fortune_lamport_vault: Protocol's fee vault for the quote mint
+- creator_vault: Pool creator's fee vault for the quote mint
+- lp_vault: Liquidity providers' fee vault
+- user_ptoken_vault: Buyer's ptoken vault with protocol
+- user_stats: Buyer's stats for the pool limits
+- ptoken_mint: Ptoken mint for prob pool
+- quote_mint: Mint the pool is priced in
+- quote: Quote mint fee accounting
+- state: State
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct Buy<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_quote_account.mint == quote_mint.key()
+    )]
+    pub user_quote_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = prob_pool.lamport_vault == pool_lamport_vault.key(),
+        constraint = prob_pool.ptoken_vault == pool_ptoken_vault.key(),
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    // Pool creator's share of the swap fees
+    #[account(
+        mut,
+        seeds = [
+            b"creator",
+            quote_mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            prob_pool.authority.as_ref()
+        ],
+        bump
+    )]
+    pub creator_vault: Box<Account<'info, TokenAccount>>,
+    // Liquidity providers' share of the swap fees
+    #[account(
+        mut,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = ptoken_mint,
+        token::authority = user_ptoken_vault,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = UserStats::LEN,
+        seeds = [b"stats", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"quote", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Owner of the ptoken vault to sell from
+- recipient: Quote token account to give proceeds to
+- pool_lamport_vault: Pool's lamport vault
+- pool_ptoken_vault: Pool's ptoken vault
+- prob_pool: Probability pool to sell to
+- fortune_lamport_vault: Protocol's fee vault for the quote mint
+- creator_vault: Pool creator's fee vault for the quote mint
+- lp_vault: Liquidity providers' fee vault
+- user_ptoken_vault: Seller's ptoken vault with protocol
+- user_stats: Seller's stats for the pool limits
+- ptoken_mint: Ptoken mint for prob pool
+- quote_mint: Mint the pool is priced in
+- quote: Quote mint fee accounting
+- state: State
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct Sell<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = quote_mint,
+        token::authority = signer)]
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = prob_pool.lamport_vault == pool_lamport_vault.key(),
+        constraint = prob_pool.ptoken_vault == pool_ptoken_vault.key(),
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    // Pool creator's share of the swap fees
+    #[account(
+        mut,
+        seeds = [
+            b"creator",
+            quote_mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            prob_pool.authority.as_ref()
+        ],
+        bump
+    )]
+    pub creator_vault: Box<Account<'info, TokenAccount>>,
+    // Liquidity providers' share of the swap fees
+    #[account(
+        mut,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = UserStats::LEN,
+        seeds = [b"stats", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"quote", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Any
+- user_quote_account: Buyer's token account to pay from
+- entry: Buyer's tickets in the raffle
+- pool_lamport_vault: Pool's lamport vault
+- pool_ptoken_vault: Pool's ptoken vault
+- prob_pool: Raffle pool to buy tickets from
+- fortune_lamport_vault: Protocol's fee vault for the quote mint
+- creator_vault: Pool creator's fee vault for the quote mint
+- user_ptoken_vault: Buyer's ptoken vault with protocol
+- user_stats: Buyer's stats for the pool limits
+- ptoken_mint: Ptoken mint for prob pool
+- quote_mint: Mint the pool is priced in
+- quote: Quote mint fee accounting
+- state: State
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct BuyTickets<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_quote_account.mint == quote_mint.key()
+    )]
+    pub user_quote_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        space = Entry::LEN,
+        payer = signer,
+        seeds = [b"entry", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub entry: Box<Account<'info, Entry>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = prob_pool.lamport_vault == pool_lamport_vault.key(),
+        constraint = prob_pool.ptoken_vault == pool_ptoken_vault.key(),
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    // Pool creator's share of the swap fees
+    #[account(
+        mut,
+        seeds = [
+            b"creator",
+            quote_mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            prob_pool.authority.as_ref()
+        ],
+        bump
+    )]
+    pub creator_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = ptoken_mint,
+        token::authority = user_ptoken_vault,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = UserStats::LEN,
+        seeds = [b"stats", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"quote", quote_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Must be the owner of the ptoken vault to burn from
+- fortune_lamport_vault: Protocol SOL vault
+- creator_sol_vault: Pool creator's SOL fee vault
+- user_ptoken_vault: Signer's ptoken vault
+- user_burn: Signer's ptoken burn vault (tokens ready to burn once here)
+- burn_request: Signer's burn request record for the pool
+- user_stats: Signer's stats for the pool limits
+- prob_pool: Probability pool to burn tokens for
+- ptoken_mint: Ptoken mint for the probability pool
+- state: State
+- quote: SOL fee accounting
+- native_mint: NATIVE MINT
+- system_program: System
+- token_program: Token
+- rent: Rent
+ */
+#[derive(Accounts)]
+pub struct RequestBurn<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"vault", native_mint.key().as_ref()],
+        bump
+    )]
+    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    // Pool creator's share of the burn costs
+    #[account(
+        mut,
+        seeds = [
+            b"creator",
+            native_mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            prob_pool.authority.as_ref()
+        ],
+        bump
+    )]
+    pub creator_sol_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = ptoken_mint,
+        token::authority = user_burn,
+        seeds = [b"burn", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_burn: Box<Account<'info, TokenAccount>>,
+    // Reused across requests, one pending at a time per user and pool
+    #[account(
+        init_if_needed,
+        space = 250,
+        payer = signer,
+        seeds = [b"request", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub burn_request: Box<Account<'info, BurnRequest>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = UserStats::LEN,
+        seeds = [b"stats", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+    #[account(
+        mut,
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [b"fortune"],
+        bump
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [b"quote", native_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
+    #[account(address = spl_token::native_mint::ID)]
+    pub native_mint: Box<Account<'info, Mint>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
 /*
-- signer: Any, becomes the authority of the program
-- spl_vault: Initial program vault for SOL
-- spl_mint: Native mint
-- state: State
+- signer: Owner of user ptoken account
+- user_ptoken_vault: User PDA ptoken vault
+- user_account: User ptoken account
+- prob_pool: Probability pool of the ptokens
+- ptoken_mint: Ptoken mint account for withdraw
 - system_program: System
 - token_program: Token
 - rent: Rent
 */
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct UserWithdraw<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
-        init_if_needed,
-        payer = signer,
-        token::mint = spl_mint,
-        token::authority = spl_vault,
-        seeds = [b"vault", spl_mint.key().as_ref()],
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
         bump
     )]
-    pub spl_vault: Box<Account<'info, TokenAccount>>,
-    #[account(address = spl_token::native_mint::ID)]
-    pub spl_mint: Box<Account<'info, Mint>>,
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
     #[account(
         init_if_needed,
-        space = 250,
         payer = signer,
-        seeds = [b"fortune"],
-        bump
+        token::mint = ptoken_mint,
+        token::authority = signer,
     )]
-    pub state: Box<Account<'info, State>>,
+    pub user_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Bound by the ptoken mint seeds, may be closed
+    pub prob_pool: UncheckedAccount<'info>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
     // System programs + sysvars
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
@@ -556,158 +3726,464 @@ pub struct Initialize<'info> {
 }
 
 /*
-- signer: Any
-- nft_account: TokenAccount with NFT
-- prob_pool: ProbPool
-- ptoken_mint: Mint for ProbPool
-- nft_vault: Pool vault for NFT
-- lamport_vault: Pool vault for lamports
-- ptoken_vault: Pool vault for ptokens
-- nft_mint: Mint for NFT stored in the pool
-- native_mint: NATIVE_MINT
-- state: State
+- signer: Seller, owner of the ptoken vault
+- user_ptoken_vault: Seller's ptoken vault with protocol
+- listing: Seller's listing in the pool
+- listing_vault: Escrow of the listed ptokens
+- bid_vault: Escrow of the best bid in the quote mint
+- prob_pool: Probability pool of the ptokens
+- ptoken_mint: Ptoken mint for prob pool
+- quote_mint: Mint the pool is priced in
 - system_program: System
 - token_program: Token
 - rent: Rent
 */
 #[derive(Accounts)]
-pub struct CreatePool<'info> {
+pub struct ListPtokens<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
-    // Holds the NFT, owned by the signer
     #[account(
         mut,
-        constraint = nft_account.owner == signer.key(),
-        constraint = nft_account.mint == nft_mint.key(),
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        bump
     )]
-    pub nft_account: Box<Account<'info, TokenAccount>>,
-    // Prob pools are generated from a keypair
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    // One listing per seller and pool
     #[account(
         init,
-        space = 350,
-        payer = signer
+        payer = signer,
+        space = Listing::LEN,
+        seeds = [b"listing", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
     )]
-    pub prob_pool: Box<Account<'info, ProbPool>>,
-    // Ptoken mint is unique for each pool
+    pub listing: Box<Account<'info, Listing>>,
+    #[account(
+        init,
+        payer = signer,
+        token::mint = ptoken_mint,
+        token::authority = listing_vault,
+        seeds = [b"escrow", listing.key().as_ref()],
+        bump
+    )]
+    pub listing_vault: Box<Account<'info, TokenAccount>>,
     #[account(
         init,
         payer = signer,
+        token::mint = quote_mint,
+        token::authority = bid_vault,
+        seeds = [b"bid", listing.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
         seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
         bump,
-        mint::decimals = 0,
-        mint::authority = ptoken_mint
     )]
     pub ptoken_mint: Box<Account<'info, Mint>>,
-    // Vault for nft
+    pub quote_mint: Box<Account<'info, Mint>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Seller
+- listing: Listing to reprice
+- seller_burn_request: Seller's burn request for the pool, locks the listing while pending
+*/
+#[derive(Accounts)]
+pub struct UpdateAsk<'info> {
+    pub signer: Signer<'info>,
+    #[account(mut)]
+    pub listing: Box<Account<'info, Listing>>,
+    /// CHECK: Seller's burn request for the pool, may not exist yet
     #[account(
-        init,
-        payer = signer,
-        token::mint = nft_mint,
-        token::authority = nft_vault,
-        seeds = [b"vault", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
+        seeds = [b"request", listing.prob_pool.as_ref(), listing.seller.as_ref()],
         bump
     )]
-    pub nft_vault: Box<Account<'info, TokenAccount>>,
-    // Vault for SPL token
+    pub seller_burn_request: UncheckedAccount<'info>,
+}
+
+/*
+- signer: Seller
+- user_ptoken_vault: Seller's ptoken vault with protocol
+- listing: Listing to cancel
+- seller_burn_request: Seller's burn request for the pool, locks the listing while pending
+- listing_vault: Escrow of the listed ptokens
+- bid_vault: Escrow of the best bid
+- bid_refund: Best bidder's quote token account, any quote account without a bid
+- prob_pool: Probability pool of the ptokens
+- ptoken_mint: Ptoken mint for prob pool
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct CancelListing<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
     #[account(
-        init,
-        payer = signer,
-        token::mint = native_mint,
-        token::authority = lamport_vault,
-        seeds = [b"vault", native_mint.key().as_ref(), prob_pool.key().as_ref()],
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
         bump
     )]
-    pub lamport_vault: Box<Account<'info, TokenAccount>>,
-    // Vault for ptokens
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
     #[account(
-        init,
+        mut,
+        close = signer,
+        seeds = [b"listing", prob_pool.key().as_ref(), listing.seller.as_ref()],
+        bump
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+    /// CHECK: Seller's burn request for the pool, may not exist yet
+    #[account(
+        seeds = [b"request", listing.prob_pool.as_ref(), listing.seller.as_ref()],
+        bump
+    )]
+    pub seller_burn_request: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow", listing.key().as_ref()],
+        bump
+    )]
+    pub listing_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"bid", listing.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = bid_refund.mint == prob_pool.quote_mint,
+        constraint = listing.bid == 0 || bid_refund.owner == listing.bidder
+    )]
+    pub bid_refund: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- signer: Buyer
+- user_quote_account: Buyer's quote token account paying the ask
+- seller: Seller, receives the listing rent
+- seller_quote_account: Seller's quote token account receiving the ask
+- user_ptoken_vault: Buyer's ptoken vault with protocol
+- user_stats: Buyer's stats for the pool limits
+- listing: Listing to fill
+- seller_burn_request: Seller's burn request for the pool, locks the listing while pending
+- listing_vault: Escrow of the listed ptokens
+- bid_vault: Escrow of the best bid
+- bid_refund: Best bidder's quote token account, any quote account without a bid
+- prob_pool: Probability pool of the ptokens
+- ptoken_mint: Ptoken mint for prob pool
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct FillListing<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_quote_account.mint == prob_pool.quote_mint
+    )]
+    pub user_quote_account: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Bound to the listing seller
+    #[account(
+        mut,
+        constraint = listing.seller == seller.key()
+    )]
+    pub seller: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = seller_quote_account.mint == prob_pool.quote_mint,
+        constraint = seller_quote_account.owner == listing.seller
+    )]
+    pub seller_quote_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
         payer = signer,
         token::mint = ptoken_mint,
-        token::authority = ptoken_vault,
-        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        token::authority = user_ptoken_vault,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
         bump
     )]
-    pub ptoken_vault: Box<Account<'info, TokenAccount>>,
-    // Mint address identifies the NFT
-    #[account()]
-    pub nft_mint: Box<Account<'info, Mint>>,
-    #[account(address = spl_token::native_mint::ID)]
-    pub native_mint: Box<Account<'info, Mint>>,
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = UserStats::LEN,
+        seeds = [b"stats", prob_pool.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+    #[account(
+        mut,
+        close = seller,
+        seeds = [b"listing", prob_pool.key().as_ref(), listing.seller.as_ref()],
+        bump
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+    /// CHECK: Seller's burn request for the pool, may not exist yet
+    #[account(
+        seeds = [b"request", listing.prob_pool.as_ref(), listing.seller.as_ref()],
+        bump
+    )]
+    pub seller_burn_request: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"escrow", listing.key().as_ref()],
+        bump
+    )]
+    pub listing_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"bid", listing.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = bid_refund.mint == prob_pool.quote_mint,
+        constraint = listing.bid == 0 || bid_refund.owner == listing.bidder
+    )]
+    pub bid_refund: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key()
+        )]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Bidder
+- user_quote_account: Bidder's quote token account paying the bid
+- listing: Listing to bid on
+- seller_burn_request: Seller's burn request for the pool, locks the listing while pending
+- bid_vault: Escrow of the best bid
+- bid_refund: Quote token account of the bidder being beaten, any quote account without a bid
+- prob_pool: Probability pool of the ptokens
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = user_quote_account.mint == prob_pool.quote_mint
+    )]
+    pub user_quote_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"listing", prob_pool.key().as_ref(), listing.seller.as_ref()],
+        bump
+    )]
+    pub listing: Box<Account<'info, Listing>>,
+    /// CHECK: Seller's burn request for the pool, may not exist yet
+    #[account(
+        seeds = [b"request", listing.prob_pool.as_ref(), listing.seller.as_ref()],
+        bump
+    )]
+    pub seller_burn_request: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"bid", listing.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = bid_refund.mint == prob_pool.quote_mint,
+        constraint = listing.bid == 0 || bid_refund.owner == listing.bidder
+    )]
+    pub bid_refund: Box<Account<'info, TokenAccount>>,
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- signer: Best bidder
+- recipient: Quote token account to return the bid to
+- listing: Listing bid on
+- bid_vault: Escrow of the best bid
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct WithdrawBid<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = recipient.mint == bid_vault.mint
+    )]
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    // Bidder is the default pubkey without a bid
+    #[account(
+        mut,
+        constraint = listing.bidder == signer.key()
+    )]
+    pub listing: Box<Account<'info, Listing>>,
     #[account(
-        seeds = [b"fortune"],
+        mut,
+        seeds = [b"bid", listing.key().as_ref()],
         bump
     )]
-    pub state: Box<Account<'info, State>>,
-    // System programs + sysvars
-    pub system_program: Program<'info, System>,
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
     pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 /*
-- signer: Any
-- pool_lamport_vault: Pool's lamport vault
-- pool_ptoken_vault: Pool's ptoken vault
-- prob_pool: Probability pool to buy from
-- fortune_lamport_vault: Protocol's lamport vault
-- user_ptoken_vault: Buyer's ptoken vault with protocol
+- signer: Seller
+- recipient: Quote token account to receive the bid
+- bidder: Best bidder
+- bidder_ptoken_vault: Best bidder's ptoken vault with protocol
+- bidder_stats: Best bidder's stats for the pool limits
+- listing: Listing to sell
+- seller_burn_request: Seller's burn request for the pool, locks the listing while pending
+- listing_vault: Escrow of the listed ptokens
+- bid_vault: Escrow of the best bid
+- prob_pool: Probability pool of the ptokens
 - ptoken_mint: Ptoken mint for prob pool
-- native_mint: NATIVE_MINT
-- state: State
 - system_program: System
 - token_program: Token
 - rent: Rent
 */
 #[derive(Accounts)]
-pub struct Buy<'info> {
+pub struct AcceptBid<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
         mut,
-        seeds = [b"vault", native_mint.key().as_ref(), prob_pool.key().as_ref()],
+        constraint = recipient.mint == prob_pool.quote_mint
+    )]
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Bound to the listing's best bidder
+    #[account(constraint = listing.bidder == bidder.key())]
+    pub bidder: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = ptoken_mint,
+        token::authority = bidder_ptoken_vault,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), bidder.key().as_ref()],
         bump
     )]
-    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
+    pub bidder_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = UserStats::LEN,
+        seeds = [b"stats", prob_pool.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    pub bidder_stats: Box<Account<'info, UserStats>>,
     #[account(
         mut,
-        seeds = [b"vault", ptoken_mint.key().as_ref(), prob_pool.key().as_ref()],
+        close = signer,
+        seeds = [b"listing", prob_pool.key().as_ref(), listing.seller.as_ref()],
         bump
     )]
-    pub pool_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    pub listing: Box<Account<'info, Listing>>,
+    /// CHECK: Seller's burn request for the pool, may not exist yet
+    #[account(
+        seeds = [b"request", listing.prob_pool.as_ref(), listing.seller.as_ref()],
+        bump
+    )]
+    pub seller_burn_request: UncheckedAccount<'info>,
     #[account(
         mut,
-        constraint = prob_pool.lamport_vault == pool_lamport_vault.key(),
-        constraint = prob_pool.ptoken_vault == pool_ptoken_vault.key(),
+        seeds = [b"escrow", listing.key().as_ref()],
+        bump
+    )]
+    pub listing_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"bid", listing.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
         constraint = prob_pool.ptoken_mint == ptoken_mint.key()
         )]
     pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        bump,
+    )]
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- user: Pubkey of user we are doing the burn on behalf
+- burn_request: User's pending burn request, marked settled
+- user_burn: User's burn account
+- prob_pool: Probability pool
+- ptoken_mint: Ptoken mint in the user burn
+- slot_hashes: SlotHashes sysvar
+- instructions: Instructions sysvar, holds the oracle signature check
+- system_program: System
+- token_program: Token
+- rent: Rent
+*/
+#[derive(Accounts)]
+pub struct ExecuteBurn<'info> {
+    /// CHECK: Bound by the request and burn vault seeds
+    pub user: UncheckedAccount<'info>,
     #[account(
         mut,
-        seeds = [b"vault", native_mint.key().as_ref()],
-        bump
+        seeds = [b"request", prob_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = burn_request.user == user.key(),
+        constraint = burn_request.prob_pool == prob_pool.key()
     )]
-    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    pub burn_request: Box<Account<'info, BurnRequest>>,
     #[account(
-        init_if_needed,
-        payer = signer,
-        token::mint = ptoken_mint,
-        token::authority = user_ptoken_vault,
-        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        mut,
+        seeds = [b"burn", prob_pool.key().as_ref(), user.key().as_ref()],
         bump
     )]
-    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    pub user_burn: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
     #[account(
+        mut,
         seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
         bump,
     )]
     pub ptoken_mint: Box<Account<'info, Mint>>,
-    #[account(address = spl_token::native_mint::ID)]
-    pub native_mint: Box<Account<'info, Mint>>,
+    /// CHECK: Constraint
     #[account(
-        seeds = [b"fortune"],
-        bump
+        constraint = slot_hashes.key() == sysvar::slot_hashes::SlotHashes::id()
     )]
-    pub state: Box<Account<'info, State>>,
+    pub slot_hashes: UncheckedAccount<'info>,
+    /// CHECK: Constraint
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
     // System programs + sysvars
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
@@ -715,43 +4191,54 @@ pub struct Buy<'info> {
 }
 
 /*
-- signer: Must be the owner of the ptoken vault to burn from
-- fortune_lamport_vault: Protocol SOL vault
-- user_ptoken_vault: Signer's ptoken vault
-- user_burn: Signer's ptoken burn vault (tokens ready to burn once here)
-- prob_pool: Probability pool to burn tokens for
+- signer: Anyone once the request timed out or every prize is won
+- user: Owner of the burn request
+- recipient: User's SOL token account to refund the burn cost to
+- burn_request: User's pending burn request, marked cancelled
+- user_burn: User's burn account holding the requested ptokens
+- user_ptoken_vault: User's ptoken vault to return them to
+- prob_pool: Probability pool
 - ptoken_mint: Ptoken mint for the probability pool
-- state: State
+- fortune_lamport_vault: Protocol SOL vault holding the burn cost
+- creator_sol_vault: Pool creator's SOL fee vault holding its share
+- quote: SOL fee accounting
 - native_mint: NATIVE MINT
 - system_program: System
 - token_program: Token
 - rent: Rent
- */
+*/
 #[derive(Accounts)]
-pub struct RequestBurn<'info> {
+pub struct CancelBurn<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
+    /// CHECK: Bound by the request and burn vault seeds
+    pub user: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        token::mint = native_mint,
+        token::authority = user)]
+    pub recipient: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        seeds = [b"vault", native_mint.key().as_ref()],
-        bump
+        seeds = [b"request", prob_pool.key().as_ref(), user.key().as_ref()],
+        bump,
+        constraint = burn_request.user == user.key(),
+        constraint = burn_request.prob_pool == prob_pool.key()
     )]
-    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    pub burn_request: Box<Account<'info, BurnRequest>>,
     #[account(
         mut,
-        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        seeds = [b"burn", prob_pool.key().as_ref(), user.key().as_ref()],
         bump
     )]
-    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    pub user_burn: Box<Account<'info, TokenAccount>>,
     #[account(
-        init_if_needed,
-        payer = signer,
-        token::mint = ptoken_mint,
-        token::authority = user_burn,
-        seeds = [b"burn", prob_pool.key().as_ref(), signer.key().as_ref()],
+        mut,
+        seeds = [b"vault", ptoken_mint.key().as_ref(), user.key().as_ref()],
         bump
     )]
-    pub user_burn: Box<Account<'info, TokenAccount>>,
+    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
         constraint = prob_pool.ptoken_mint == ptoken_mint.key()
@@ -763,10 +4250,29 @@ pub struct RequestBurn<'info> {
     )]
     pub ptoken_mint: Box<Account<'info, Mint>>,
     #[account(
-        seeds = [b"fortune"],
+        mut,
+        seeds = [b"vault", native_mint.key().as_ref()],
         bump
     )]
-    pub state: Box<Account<'info, State>>,
+    pub fortune_lamport_vault: Box<Account<'info, TokenAccount>>,
+    // Pool creator's share of the burn costs
+    #[account(
+        mut,
+        seeds = [
+            b"creator",
+            native_mint.key().as_ref(),
+            prob_pool.key().as_ref(),
+            prob_pool.authority.as_ref()
+        ],
+        bump
+    )]
+    pub creator_sol_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"quote", native_mint.key().as_ref()],
+        bump
+    )]
+    pub quote: Box<Account<'info, Quote>>,
     #[account(address = spl_token::native_mint::ID)]
     pub native_mint: Box<Account<'info, Mint>>,
     // System programs + sysvars
@@ -776,138 +4282,224 @@ pub struct RequestBurn<'info> {
 }
 
 /*
-- signer: Owner of user ptoken account
-- user_ptoken_vault: User PDA ptoken vault
-- user_account: User ptoken account
-- ptoken_mint: Ptoken mint account for withdraw
+- signer: Any, pays for the draw record
+- draw: Raffle draw record for the pool
+- prob_pool: Raffle pool to draw
+- state: State
 - system_program: System
-- token_program: Token
 - rent: Rent
 */
 #[derive(Accounts)]
-pub struct UserWithdraw<'info> {
+pub struct RequestDraw<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
-        mut,
-        seeds = [b"vault", ptoken_mint.key().as_ref(), signer.key().as_ref()],
+        init_if_needed,
+        space = 200,
+        payer = signer,
+        seeds = [b"draw", prob_pool.key().as_ref()],
         bump
     )]
-    pub user_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    pub draw: Box<Account<'info, Draw>>,
+    #[account(mut)]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
     #[account(
-        init_if_needed,
-        payer = signer,
-        token::mint = ptoken_mint,
-        token::authority = signer,
+        seeds = [b"fortune"],
+        bump
     )]
-    pub user_account: Box<Account<'info, TokenAccount>>,
-    #[account()]
-    pub ptoken_mint: Box<Account<'info, Mint>>,
+    pub state: Box<Account<'info, State>>,
     // System programs + sysvars
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 /*
-- fortune_authority: Authority on Fortune, permissioned call for now
-- user: Pubkey of user we are doing the burn on behalf
-- nft_vault: NFT prize vault for probability pool
-- user_burn: User's burn account
+- draw: Pending raffle draw, marked settled
+- entry: Entry holding the winning ticket
+- prob_pool: Raffle pool
+- slot_hashes: SlotHashes sysvar
+- instructions: Instructions sysvar, holds the oracle signature check
+*/
+#[derive(Accounts)]
+pub struct ExecuteDraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"draw", prob_pool.key().as_ref()],
+        bump,
+        constraint = draw.prob_pool == prob_pool.key()
+    )]
+    pub draw: Box<Account<'info, Draw>>,
+    #[account(constraint = entry.prob_pool == prob_pool.key())]
+    pub entry: Box<Account<'info, Entry>>,
+    #[account(mut)]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
+    /// CHECK: Constraint
+    #[account(
+        constraint = slot_hashes.key() == sysvar::slot_hashes::SlotHashes::id()
+    )]
+    pub slot_hashes: UncheckedAccount<'info>,
+    /// CHECK: Constraint
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+/*
+- signer: Must be the winner of the prize
+- nft_account: NFT account to transfer prize to
 - prob_pool: Probability pool
-- nft_mint: Mint for the prize
-- ptoken_mint: Ptoken mint in the user burn
-- state: State
+- nft_vault: NFT protocol vault
+- nft_mint: Prize mint
 - system_program: System
 - token_program: Token
 - rent: Rent
 */
 #[derive(Accounts)]
-pub struct ExecuteBurn<'info> {
+pub struct ClaimAsset<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
     #[account(
-        mut,
-        constraint = fortune_authority.key() == state.authority
-    )]
-    pub fortune_authority: Signer<'info>,
-    /// CHECK: This function is only runnable by Fortune admin for now
+        init_if_needed,
+        payer = signer,
+        token::mint = nft_mint,
+        token::authority = signer)]
+    pub nft_account: Account<'info, TokenAccount>,
     #[account(mut)]
-    pub user: UncheckedAccount<'info>,
-    // Vault for nft
+    pub prob_pool: Box<Account<'info, ProbPool>>,
     #[account(
         mut,
-        seeds = [b"vault", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
+        seeds = [b"prize", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
         bump,
         constraint = nft_vault.mint == nft_mint.key(),
     )]
     pub nft_vault: Box<Account<'info, TokenAccount>>,
+    pub nft_mint: Box<Account<'info, Mint>>,
+    // System programs + sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/*
+- signer: Must be the winner of the SOL bounty
+- prob_pool: Probability pool
+- prize_vault: Wrapped SOL vault of the bounty, closed
+- native_mint: NATIVE_MINT
+- token_program: Token
+*/
+#[derive(Accounts)]
+pub struct ClaimSol<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(mut)]
+    pub prob_pool: Box<Account<'info, ProbPool>>,
     #[account(
         mut,
-        seeds = [b"burn", prob_pool.key().as_ref(), user.key().as_ref()],
+        seeds = [b"prize", native_mint.key().as_ref(), prob_pool.key().as_ref()],
         bump
     )]
-    pub user_burn: Box<Account<'info, TokenAccount>>,
+    pub prize_vault: Box<Account<'info, TokenAccount>>,
+    #[account(address = spl_token::native_mint::ID)]
+    pub native_mint: Box<Account<'info, Mint>>,
+    pub token_program: Program<'info, Token>,
+}
+
+/*
+- signer: Any, once the refund deadline has passed
+- nft_account: Creator's NFT account to return the first prize to
+- prob_pool: Probability pool
+- nft_vault: NFT protocol vault of the first prize
+- pool_lamport_vault: Pools lamport vault, snapshotted for refunds
+- lp_vault: Liquidity providers' vault, receives their claim
+- nft_mint: Mint of the first prize
+- quote_mint: Mint the pool is priced in
+- token_program: Token
+- remaining accounts: Vault and creator NFT account of every other prize
+*/
+#[derive(Accounts)]
+pub struct StartRefund<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = nft_account.owner == prob_pool.authority,
+        constraint = nft_account.mint == nft_mint.key(),
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        constraint = prob_pool.nft_mint == nft_mint.key()
+        constraint = prob_pool.prizes[0].mint == nft_mint.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
         )]
     pub prob_pool: Box<Account<'info, ProbPool>>,
-    pub nft_mint: Box<Account<'info, Mint>>,
     #[account(
         mut,
-        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
+        seeds = [b"prize", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
         bump,
     )]
-    pub ptoken_mint: Box<Account<'info, Mint>>,
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
     #[account(
-        seeds = [b"fortune"],
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
         bump
     )]
-    pub state: Box<Account<'info, State>>,
-    /// CHECK: Constraint
+    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
     #[account(
-        constraint = slot_hashes.key() == sysvar::slot_hashes::SlotHashes::id()
+        mut,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
     )]
-    pub slot_hashes: UncheckedAccount<'info>,
-    // System programs + sysvars
-    pub system_program: Program<'info, System>,
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
+    pub nft_mint: Box<Account<'info, Mint>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
     pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 /*
-- signer: Must be nft_authority of probability pool
-- nft_account: NFT account to transfer prize to
-- prob_pool: Probability pool
-- nft_vault: NFT protocol vault
-- nft_mint: Prize mint
+- signer: Holder redeeming ptokens
+- recipient: Quote token account to give the refund to
+- ptoken_account: Signer's ptoken vault with protocol or a ptoken account they own
+- prob_pool: Refunding probability pool
+- ptoken_mint: Ptoken mint for prob pool
+- pool_lamport_vault: Pools lamport vault
+- quote_mint: Mint the pool is priced in
 - system_program: System
 - token_program: Token
 - rent: Rent
 */
 #[derive(Accounts)]
-pub struct ClaimAsset<'info> {
+pub struct Redeem<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     #[account(
         init_if_needed,
         payer = signer,
-        token::mint = nft_mint,
+        token::mint = quote_mint,
         token::authority = signer)]
-    pub nft_account: Account<'info, TokenAccount>,
+    pub recipient: Box<Account<'info, TokenAccount>>,
+    // Owned by the signer or their vault, checked in the handler
+    #[account(
+        mut,
+        constraint = ptoken_account.mint == ptoken_mint.key()
+    )]
+    pub ptoken_account: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        constraint = prob_pool.nft_mint == nft_mint.key(),
-        constraint = prob_pool.nft_authority == signer.key()
+        constraint = prob_pool.ptoken_mint == ptoken_mint.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key()
         )]
     pub prob_pool: Box<Account<'info, ProbPool>>,
     #[account(
         mut,
-        seeds = [b"vault", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
+        seeds = ["mint".as_bytes(), prob_pool.key().as_ref()],
         bump,
-        constraint = nft_vault.mint == nft_mint.key(),
     )]
-    pub nft_vault: Box<Account<'info, TokenAccount>>,
-    pub nft_mint: Box<Account<'info, Mint>>,
+    pub ptoken_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
     // System programs + sysvars
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
@@ -916,18 +4508,21 @@ pub struct ClaimAsset<'info> {
 
 /*
 - signer: Owner of the pool
-- recipient: SOL account to give proceeds to
-- nft_account: NFT account to give prize back to (if needed)
+- recipient: Quote token account to give proceeds to
+- nft_account: NFT account to give the first prize back to (if needed)
 - prob_pool: Probability pool
+- pool_metadata: Name, URI and category of the pool
 - ptoken_mint: Ptoken mint for pool
-- nft_vault: NFT protocol prize vault
+- nft_vault: NFT protocol vault of the first prize
 - pool_lamport_vault: Pools lamport vault for AMM
 - pool_ptoken_vault: Pools ptoken vault for AMM
-- nft_mint: Mint of prize
-- native_mint: Sol
+- lp_vault: Liquidity providers' vault, receives their claim
+- nft_mint: Mint of the first prize
+- quote_mint: Mint the pool is priced in
 - system_program: System
 - token_program: Token
 - rent: Rent
+- remaining accounts: Vault and creator NFT account of every other prize
 */
 #[derive(Accounts)]
 pub struct ClosePool<'info> {
@@ -936,7 +4531,7 @@ pub struct ClosePool<'info> {
     #[account(
         init_if_needed,
         payer = signer,
-        token::mint = native_mint,
+        token::mint = quote_mint,
         token::authority = signer)]
     pub recipient: Account<'info, TokenAccount>,
     #[account(
@@ -950,8 +4545,16 @@ pub struct ClosePool<'info> {
         mut,
         close = signer,
         constraint = prob_pool.authority == signer.key(),
-        constraint = prob_pool.nft_mint == nft_mint.key())]
+        constraint = prob_pool.prizes[0].mint == nft_mint.key(),
+        constraint = prob_pool.quote_mint == quote_mint.key())]
     pub prob_pool: Box<Account<'info, ProbPool>>,
+    #[account(
+        mut,
+        close = signer,
+        seeds = [b"metadata", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub pool_metadata: Box<Account<'info, PoolMetadata>>,
     // Ptoken mint is unique for each pool
     #[account(
         mut,
@@ -962,14 +4565,14 @@ pub struct ClosePool<'info> {
     // Vault for nft
     #[account(
         mut,
-        seeds = [b"vault", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
+        seeds = [b"prize", nft_mint.key().as_ref(), prob_pool.key().as_ref()],
         bump,
     )]
     pub nft_vault: Box<Account<'info, TokenAccount>>,
     // Vault for SPL token
     #[account(
         mut,
-        seeds = [b"vault", native_mint.key().as_ref(), prob_pool.key().as_ref()],
+        seeds = [b"vault", quote_mint.key().as_ref(), prob_pool.key().as_ref()],
         bump
     )]
     pub pool_lamport_vault: Box<Account<'info, TokenAccount>>,
@@ -980,11 +4583,17 @@ pub struct ClosePool<'info> {
         bump
     )]
     pub pool_ptoken_vault: Box<Account<'info, TokenAccount>>,
+    // Closed with the pool unless providers have something to redeem
+    #[account(
+        mut,
+        seeds = [b"lp_vault", prob_pool.key().as_ref()],
+        bump
+    )]
+    pub lp_vault: Box<Account<'info, TokenAccount>>,
     // Mint address identifies the NFT
     #[account()]
     pub nft_mint: Box<Account<'info, Mint>>,
-    #[account(address = spl_token::native_mint::ID)]
-    pub native_mint: Box<Account<'info, Mint>>,
+    pub quote_mint: Box<Account<'info, Mint>>,
     // System programs + sysvars
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
@@ -995,16 +4604,120 @@ pub struct ClosePool<'info> {
 // Prob pool is an AMM: pToken/SPL
 pub struct ProbPool {
     authority: Pubkey,
-    nft_authority: Pubkey,
     lamport_vault: Pubkey,
     ptoken_vault: Pubkey,
     ptoken_mint: Pubkey,
-    nft_mint: Pubkey,
-    claimed: bool,
-    to_claim: bool,
+    // Mint the pool is priced in, lamport amounts are in its base units
+    quote_mint: Pubkey,
+    // At most MAX_PRIZES, each held in its own vault
+    prizes: Vec<Prize>,
     lamport_supply: u64,
     ptoken_supply: u64,
+    // Ptokens minted at creation, the fixed odds denominator
+    initial_ptoken_supply: u64,
+    odds_model: OddsModel,
     outstanding_ptokens: u64,
+    burn_nonce: u64,
+    pending_burns: u64,
+    // Trading and burns close at open_until, a win must be claimed by claim_deadline
+    open_until: i64,
+    claim_deadline: Option<i64>,
+    // Refund mode, entered after the deadline without a winner
+    refund_deadline: i64,
+    refunding: bool,
+    refund_lamports: u64,
+    refund_ptokens: u64,
+    // Ptokens not redeemed by then are forfeited when the pool closes
+    redeem_deadline: i64,
+    mode: PoolMode,
+    // Raffle ticket price in quote base units and users holding tickets
+    ticket_price: u64,
+    entries: u64,
+    // Creator overrides of the protocol fees, clamped to the State bounds
+    swap_fee: Option<u64>,
+    burn_cost: Option<u64>,
+    // Shares of the lamport supply, the creator's seed liquidity holds the initial
+    // lamport supply in shares and providers the LP mint supply
+    seed_shares: u64,
+    lp_shares: u64,
+    // Bonding curve swaps are priced on, curves other than constant product scale off
+    // the lamport supply at creation
+    curve: Curve,
+    initial_lamport_supply: u64,
+    // Net swap proceeds below which no burn can win, holders are refunded if it is
+    // not reached by open_until
+    reserve: u64,
+    limits: PoolLimits,
+}
+
+#[account]
+// Creator chosen identity of a pool, PDA of the pool
+pub struct PoolMetadata {
+    prob_pool: Pubkey,
+    name: String,
+    uri: String,
+    category: String,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub struct Prize {
+    // Native mint for SOL bounties
+    mint: Pubkey,
+    kind: PrizeKind,
+    // Base units of the mint held in the prize vault
+    amount: u64,
+    // Chance of being the prize a win takes, relative to the other open prizes
+    weight: u64,
+    status: PrizeStatus,
+    winner: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PrizeKind {
+    // Single token of a zero decimal mint
+    Nft,
+    // Any amount of an SPL mint
+    Token,
+    // Lamports, wrapped into the prize vault
+    Sol,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PrizeStatus {
+    Open,
+    // Waiting for the winner to claim it
+    Won,
+    Claimed,
+    // Back with the creator after a refund or close
+    Returned,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PoolMode {
+    // AMM trading with burns for a chance to win at any time
+    Continuous,
+    // Fixed-price tickets, a single draw once sold out or closed
+    Raffle,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OddsModel {
+    // Burns are drawn against the ptokens left in the pool, odds improve as it sells
+    RemainingSupply,
+    // Burns are drawn against every ptoken minted, odds stay the same
+    FixedSupply,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum Curve {
+    // k = ptoken_supply * lamport_supply, cheap first ptokens and steep last ones
+    ConstantProduct,
+    // Price climbs in a straight line to five times the opening price
+    Linear,
+    // Price climbs e-fold every quarter of the supply
+    Exponential,
+    // Logarithmic market scoring rule, price levels off towards twice the opening price
+    Lmsr,
 }
 
 #[account]
@@ -1014,8 +4727,800 @@ pub struct State {
     burn_cost: u64,
     fee_scalar: u64,
     swap_fee: u64,
-    lamport_init_min: u64,
-    lamport_init_max: u64,
     ptoken_init_max: u64,
     ptoken_init_min: u64,
+    oracle: Pubkey,
+    pending_authority: Pubkey,
+    // Range pools may override swap_fee and burn_cost within
+    swap_fee_min: u64,
+    swap_fee_max: u64,
+    burn_cost_min: u64,
+    burn_cost_max: u64,
+    // Share of swap fees and burn costs paid to the pool creator
+    creator_fee_bps: u64,
+}
+
+#[account]
+// Verified collections NFT prizes must belong to while gated
+pub struct Collections {
+    gated: bool,
+    keys: Vec<Pubkey>,
+}
+
+#[account]
+// Quote mint pools may be priced in, with cumulative protocol fee vault accounting
+pub struct Quote {
+    mint: Pubkey,
+    vault: Pubkey,
+    fees_collected: u64,
+    fees_withdrawn: u64,
+    // Initial pool liquidity in the quote mint's base units
+    lamport_init_min: u64,
+    lamport_init_max: u64,
+}
+
+impl ProbPool {
+    pub const LEN: usize = 8
+        // authority, vaults, ptoken and quote mints
+        + 5 * 32
+        + 4
+        + MAX_PRIZES * Prize::LEN
+        // Supplies, odds model, outstanding ptokens, nonce and pending burns
+        + 3 * 8
+        + 1
+        + 3 * 8
+        // Deadlines and refund accounting
+        + 8
+        + 9
+        + 8
+        + 1
+        + 3 * 8
+        // Mode, ticket price and entries
+        + 1
+        + 2 * 8
+        // Fee overrides
+        + 2 * 9
+        // Seed and LP shares
+        + 2 * 8
+        // Curve and initial lamport supply
+        + 1
+        + 8
+        // Reserve and wallet limits
+        + 8
+        + PoolLimits::LEN;
+
+    // Swap fee charged by the pool
+    fn swap_fee(&self, state: &State) -> u64 {
+        self.swap_fee.map_or(state.swap_fee, |fee| {
+            fee.clamp(state.swap_fee_min, state.swap_fee_max)
+        })
+    }
+
+    // Burn cost charged by the pool
+    fn burn_cost(&self, state: &State) -> u64 {
+        self.burn_cost.map_or(state.burn_cost, |cost| {
+            cost.clamp(state.burn_cost_min, state.burn_cost_max)
+        })
+    }
+
+    // Ptokens a burn is drawn against
+    fn odds_denominator(&self) -> u64 {
+        match self.odds_model {
+            OddsModel::RemainingSupply => self.ptoken_supply,
+            OddsModel::FixedSupply => self.initial_ptoken_supply,
+        }
+    }
+
+    // Some prize is still there to be won
+    fn prizes_left(&self) -> bool {
+        self.prizes
+            .iter()
+            .any(|prize| prize.status == PrizeStatus::Open)
+    }
+
+    // A won prize can still be claimed
+    fn active_claim(&self, now: i64) -> bool {
+        !self.claim_expired(now)
+            && self
+                .prizes
+                .iter()
+                .any(|prize| prize.status == PrizeStatus::Won)
+    }
+
+    // Prize goes back to the creator when it was never won or its claim lapsed
+    fn returnable(&self, prize: &Prize, now: i64) -> bool {
+        match prize.status {
+            PrizeStatus::Open => true,
+            PrizeStatus::Won => self.claim_expired(now),
+            PrizeStatus::Claimed | PrizeStatus::Returned => false,
+        }
+    }
+
+    // Open prize a roll lands on, weighted. Requires prizes_left.
+    fn pick_prize(&self, roll: u64) -> usize {
+        let total: u128 = self
+            .prizes
+            .iter()
+            .filter(|prize| prize.status == PrizeStatus::Open)
+            .map(|prize| prize.weight as u128)
+            .sum();
+        let mut roll = roll as u128 % total;
+        for (index, prize) in self.prizes.iter().enumerate() {
+            if prize.status != PrizeStatus::Open {
+                continue;
+            }
+            if roll < prize.weight as u128 {
+                return index;
+            }
+            roll -= prize.weight as u128;
+        }
+        unreachable!("roll is below the total open weight")
+    }
+
+    // Raffle tickets leave the pool once sold and are never sold back
+    fn tickets_sold(&self) -> u64 {
+        self.initial_ptoken_supply - self.ptoken_supply
+    }
+
+    fn total_shares(&self) -> u64 {
+        self.seed_shares + self.lp_shares
+    }
+
+    // Liquidity providers' part of a swap fee, by their shares of the lamport supply
+    fn lp_fee(&self, fee: u64) -> Result<u64> {
+        if self.lp_shares == 0 || fee == 0 {
+            return Ok(0);
+        }
+        amm::pro_rata(self.lp_shares, fee, self.total_shares())
+    }
+
+    // Liquidity providers' part of the lamport supply, at most what the vault holds
+    fn lp_claim(&self, vault_amount: u64) -> Result<u64> {
+        if self.lp_shares == 0 {
+            return Ok(0);
+        }
+        Ok(
+            amm::pro_rata(self.lp_shares, self.lamport_supply, self.total_shares())?
+                .min(vault_amount),
+        )
+    }
+
+    // Net swap proceeds are enough for a burn to win. Reserve pools take no liquidity
+    // so everything the curve grew by came from buyers.
+    fn reserve_met(&self) -> bool {
+        self.lamport_supply
+            .saturating_sub(self.initial_lamport_supply)
+            >= self.reserve
+    }
+
+    // Burns are only requested once the reserve is met, sells cannot take the proceeds
+    // below it until they settle
+    fn check_reserve_withdrawal(&self, lamports: u64) -> Result<()> {
+        require!(
+            self.pending_burns == 0
+                || self
+                    .lamport_supply
+                    .saturating_sub(self.initial_lamport_supply)
+                    .saturating_sub(lamports)
+                    >= self.reserve,
+            error::FortuneError::ReserveNotMet
+        );
+        Ok(())
+    }
+
+    // Listings and liquidity deposits need ptokens that can still win
+    fn check_market(&self, now: i64) -> Result<()> {
+        require!(
+            self.prizes_left() && !self.refunding,
+            error::FortuneError::PoolClosed
+        );
+        require!(now < self.open_until, error::FortuneError::PoolExpired);
+        // Raffle tickets are held by entries, not ptokens
+        require!(
+            self.mode == PoolMode::Continuous,
+            error::FortuneError::WrongPoolMode
+        );
+        Ok(())
+    }
+
+    fn claim_expired(&self, now: i64) -> bool {
+        match self.claim_deadline {
+            Some(claim_deadline) => now >= claim_deadline,
+            None => false,
+        }
+    }
+}
+
+impl Prize {
+    // Mint, kind, amount, weight, status and winner
+    const LEN: usize = 32 + 1 + 8 + 8 + 1 + 32;
+
+    // Still in its vault, open or waiting for the winner
+    fn unsettled(&self) -> bool {
+        self.status == PrizeStatus::Open || self.status == PrizeStatus::Won
+    }
+}
+
+// A seller's listing is locked while their burn request is pending, the burn's outcome
+// is public before it settles and a win ends the pool
+fn check_listing_unlocked(burn_request: &AccountInfo) -> Result<()> {
+    if *burn_request.owner == crate::ID {
+        let request = Account::<BurnRequest>::try_from(burn_request)?;
+        require!(
+            request.status != BurnStatus::Pending,
+            error::FortuneError::LockedListing
+        );
+    }
+    Ok(())
+}
+
+// Vault, creator NFT account and vault bump of every prize. The first prize uses the
+// named accounts, the others are passed in prize order as (vault, creator NFT account)
+// pairs in remaining accounts. SOL bounty vaults may already be closed.
+fn prize_vaults<'info>(
+    prob_pool: &Account<'info, ProbPool>,
+    nft_vault: AccountInfo<'info>,
+    nft_account: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<Vec<(AccountInfo<'info>, AccountInfo<'info>, u8)>> {
+    require!(
+        remaining_accounts.len() == 2 * (prob_pool.prizes.len() - 1),
+        error::FortuneError::PrizeAccounts
+    );
+    let accounts = std::iter::once((nft_vault, nft_account)).chain(
+        remaining_accounts
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone())),
+    );
+    let mut vaults = Vec::new();
+    for (prize, (vault, nft_account)) in prob_pool.prizes.iter().zip(accounts) {
+        let (vault_key, bump) = Pubkey::find_program_address(
+            &[b"prize", prize.mint.as_ref(), prob_pool.key().as_ref()],
+            &crate::ID,
+        );
+        require!(vault.key() == vault_key, error::FortuneError::PrizeAccounts);
+        // SOL bounties go back through the pool account, any account fills their slot
+        if prize.kind != PrizeKind::Sol {
+            let creator_account = Account::<TokenAccount>::try_from(&nft_account)?;
+            require!(
+                creator_account.owner == prob_pool.authority && creator_account.mint == prize.mint,
+                error::FortuneError::PrizeAccounts
+            );
+        }
+        vaults.push((vault, nft_account, bump));
+    }
+    Ok(vaults)
+}
+
+// Move a prize from the creator into its vault
+fn deposit_prize<'info>(
+    prize: &PrizeConfig,
+    signer: AccountInfo<'info>,
+    creator_account: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+) -> Result<()> {
+    match prize.kind {
+        PrizeKind::Nft | PrizeKind::Token => token::transfer(
+            CpiContext::new(
+                token_program,
+                anchor_spl::token::Transfer {
+                    from: creator_account,
+                    to: vault,
+                    authority: signer,
+                },
+            ),
+            prize.amount,
+        ),
+        PrizeKind::Sol => wrap_lamports(signer, vault, prize.amount, token_program, system_program),
+    }
+}
+
+// Pay lamports from the signer into a wrapped SOL vault
+fn wrap_lamports<'info>(
+    signer: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    amount: u64,
+    token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+) -> Result<()> {
+    invoke_signed(
+        &system_instruction::transfer(&signer.key(), &vault.key(), amount),
+        &[signer, vault.clone(), system_program],
+        &[],
+    )?;
+    // Sync native
+    invoke_signed(
+        &sync_native(&token_program.key(), &vault.key())?,
+        &[token_program, vault],
+        &[],
+    )?;
+    Ok(())
+}
+
+// Pay out of a program owned vault, signed by the vault
+fn vault_transfer<'info>(
+    token_program: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    seeds: &[&[u8]],
+    amount: u64,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program,
+            anchor_spl::token::Transfer {
+                from: vault.clone(),
+                to,
+                authority: vault,
+            },
+            &[seeds],
+        ),
+        amount,
+    )
+}
+
+// Close an emptied program owned vault
+fn vault_close<'info>(
+    token_program: AccountInfo<'info>,
+    vault: AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    token::close_account(CpiContext::new_with_signer(
+        token_program,
+        anchor_spl::token::CloseAccount {
+            account: vault.clone(),
+            destination,
+            authority: vault,
+        },
+        &[seeds],
+    ))
+}
+
+// Send every prize nobody can claim anymore back to the creator. SOL bounties are
+// unwrapped into the pool account, the creator gets them when the pool closes.
+fn return_prizes<'info>(
+    prob_pool: &mut Account<'info, ProbPool>,
+    vaults: &[(AccountInfo<'info>, AccountInfo<'info>, u8)],
+    token_program: &AccountInfo<'info>,
+    now: i64,
+) -> Result<()> {
+    let pool_key = prob_pool.key();
+    for (index, (vault, nft_account, bump)) in vaults.iter().enumerate() {
+        if !prob_pool.returnable(&prob_pool.prizes[index], now) {
+            continue;
+        }
+        let seeds: &[&[u8]] = &[
+            &b"prize"[..],
+            prob_pool.prizes[index].mint.as_ref(),
+            pool_key.as_ref(),
+            &[*bump],
+        ];
+        if prob_pool.prizes[index].kind == PrizeKind::Sol {
+            vault_close(
+                token_program.clone(),
+                vault.clone(),
+                prob_pool.to_account_info(),
+                seeds,
+            )?;
+        } else {
+            vault_transfer(
+                token_program.clone(),
+                vault.clone(),
+                nft_account.clone(),
+                seeds,
+                prob_pool.prizes[index].amount,
+            )?;
+        }
+        prob_pool.prizes[index].status = PrizeStatus::Returned;
+    }
+    Ok(())
+}
+
+impl State {
+    fn set_config(&mut self, config: &StateConfig) {
+        self.burn_cost = config.burn_cost;
+        self.fee_scalar = config.fee_scalar;
+        self.swap_fee = config.swap_fee;
+        self.ptoken_init_max = config.ptoken_init_max;
+        self.ptoken_init_min = config.ptoken_init_min;
+        self.swap_fee_min = config.swap_fee_min;
+        self.swap_fee_max = config.swap_fee_max;
+        self.burn_cost_min = config.burn_cost_min;
+        self.burn_cost_max = config.burn_cost_max;
+        self.creator_fee_bps = config.creator_fee_bps;
+    }
+
+    // Pool creator's share of a fee
+    fn creator_fee(&self, fee: u64) -> Result<u64> {
+        amm::fee(fee, self.creator_fee_bps, BPS_SCALAR)
+    }
+}
+
+impl PoolMetadata {
+    pub const LEN: usize = 8 + 32 + (4 + MAX_NAME_LEN) + (4 + MAX_URI_LEN) + (4 + MAX_CATEGORY_LEN);
+
+    fn set(&mut self, config: &PoolMetadataConfig) {
+        self.name = config.name.clone();
+        self.uri = config.uri.clone();
+        self.category = config.category.clone();
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+// Admin settable protocol parameters
+pub struct StateConfig {
+    pub swap_fee: u64,
+    pub burn_cost: u64,
+    pub fee_scalar: u64,
+    pub ptoken_init_max: u64,
+    pub ptoken_init_min: u64,
+    pub swap_fee_min: u64,
+    pub swap_fee_max: u64,
+    pub burn_cost_min: u64,
+    pub burn_cost_max: u64,
+    pub creator_fee_bps: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+// Admin settable quote mint parameters
+pub struct QuoteConfig {
+    pub lamport_init_min: u64,
+    pub lamport_init_max: u64,
+}
+
+impl QuoteConfig {
+    fn validate(&self) -> Result<()> {
+        require!(
+            self.lamport_init_min < self.lamport_init_max,
+            error::FortuneError::InvalidInitRange
+        );
+        Ok(())
+    }
+}
+
+impl Quote {
+    pub const LEN: usize = 8 + 2 * 32 + 4 * 8;
+
+    fn set_config(&mut self, config: &QuoteConfig) {
+        self.lamport_init_min = config.lamport_init_min;
+        self.lamport_init_max = config.lamport_init_max;
+    }
+
+    // Protocol share of a fee paid into the vault
+    fn collect(&mut self, fee: u64) -> Result<()> {
+        self.fees_collected = self
+            .fees_collected
+            .checked_add(fee)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        Ok(())
+    }
+
+    fn withdraw(&mut self, amount: u64) -> Result<()> {
+        self.fees_withdrawn = self
+            .fees_withdrawn
+            .checked_add(amount)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+impl Collections {
+    // NFT mints need a verified collection on the allowlist while gated, whatever kind
+    // of prize they are declared as
+    fn check(&self, mint: &Account<Mint>, metadata: &AccountInfo) -> Result<()> {
+        if !self.gated || mint.decimals != 0 || mint.supply != 1 {
+            return Ok(());
+        }
+        let collection = metadata::verified_collection(metadata, &mint.key())?;
+        require!(
+            matches!(collection, Some(key) if self.keys.contains(&key)),
+            error::FortuneError::UnverifiedCollection
+        );
+        Ok(())
+    }
+}
+
+impl StateConfig {
+    fn validate(&self) -> Result<()> {
+        require!(self.fee_scalar > 0, error::FortuneError::InvalidFeeScalar);
+        require!(
+            self.swap_fee <= self.fee_scalar,
+            error::FortuneError::InvalidSwapFee
+        );
+        require!(
+            self.ptoken_init_min < self.ptoken_init_max,
+            error::FortuneError::InvalidInitRange
+        );
+        // Default fees sit within the range pools may pick from
+        require!(
+            self.swap_fee_min <= self.swap_fee
+                && self.swap_fee <= self.swap_fee_max
+                && self.swap_fee_max <= self.fee_scalar,
+            error::FortuneError::InvalidFeeBounds
+        );
+        require!(
+            self.burn_cost_min <= self.burn_cost && self.burn_cost <= self.burn_cost_max,
+            error::FortuneError::InvalidFeeBounds
+        );
+        require!(
+            self.creator_fee_bps <= BPS_SCALAR,
+            error::FortuneError::InvalidCreatorFee
+        );
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+// Creator chosen pool parameters
+pub struct PoolConfig {
+    pub lamport_amount: u64,
+    pub ptoken_amount: u64,
+    pub open_until: i64,
+    pub claim_deadline: Option<i64>,
+    pub refund_deadline: i64,
+    pub odds_model: OddsModel,
+    // Ignored in raffle mode
+    pub curve: Curve,
+    // Quote base units the pool must net from swaps before a burn can win, 0 for none.
+    // Pools with a reserve take no liquidity.
+    pub reserve: u64,
+    pub limits: PoolLimits,
+    pub mode: PoolMode,
+    // Ignored outside of raffle mode
+    pub ticket_price: u64,
+    // Prize the pool is created with
+    pub prize: PrizeConfig,
+    pub metadata: PoolMetadataConfig,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+// Creator chosen pool identity and fee overrides, None uses the protocol fee
+pub struct PoolMetadataConfig {
+    pub name: String,
+    pub uri: String,
+    pub category: String,
+    pub swap_fee: Option<u64>,
+    pub burn_cost: Option<u64>,
+}
+
+impl PoolMetadataConfig {
+    fn validate(&self, state: &State) -> Result<()> {
+        require!(
+            self.name.len() <= MAX_NAME_LEN
+                && self.uri.len() <= MAX_URI_LEN
+                && self.category.len() <= MAX_CATEGORY_LEN,
+            error::FortuneError::MetadataTooLong
+        );
+        if let Some(swap_fee) = self.swap_fee {
+            require!(
+                state.swap_fee_min <= swap_fee && swap_fee <= state.swap_fee_max,
+                error::FortuneError::FeeOutOfBounds
+            );
+        }
+        if let Some(burn_cost) = self.burn_cost {
+            require!(
+                state.burn_cost_min <= burn_cost && burn_cost <= state.burn_cost_max,
+                error::FortuneError::FeeOutOfBounds
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+// Prize put up by the creator
+pub struct PrizeConfig {
+    pub kind: PrizeKind,
+    pub amount: u64,
+    pub weight: u64,
+}
+
+impl PrizeConfig {
+    // Mint and creator balance match the prize kind, SOL bounties are paid from lamports
+    fn validate(&self, mint: &Account<Mint>, balance: u64) -> Result<()> {
+        require!(
+            self.amount > 0 && self.weight > 0,
+            error::FortuneError::InvalidAmount
+        );
+        let valid = match self.kind {
+            PrizeKind::Nft => mint.decimals == 0 && mint.supply == 1 && self.amount == 1,
+            PrizeKind::Token => true,
+            PrizeKind::Sol => mint.key() == spl_token::native_mint::ID,
+        };
+        require!(valid, error::FortuneError::InvalidPrize);
+        if self.kind != PrizeKind::Sol {
+            require!(balance >= self.amount, error::FortuneError::InvalidPrize);
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+// Per wallet limits against a single wallet taking over a pool, None leaves one off
+pub struct PoolLimits {
+    // Ptokens a wallet may buy from the pool or listings, less those it sold back to the pool
+    pub max_wallet_ptokens: Option<u64>,
+    // Ptokens per burn request, MAX_BURN_AMOUNT caps it either way
+    pub max_burn: Option<u64>,
+    // Slots a wallet waits between burn requests
+    pub burn_cooldown: Option<u64>,
+}
+
+impl PoolLimits {
+    const LEN: usize = 3 * 9;
+
+    fn validate(&self) -> Result<()> {
+        require!(
+            self.max_wallet_ptokens != Some(0) && self.max_burn != Some(0),
+            error::FortuneError::InvalidAmount
+        );
+        Ok(())
+    }
+
+    fn max_burn(&self) -> u64 {
+        self.max_burn
+            .map_or(MAX_BURN_AMOUNT, |max| max.min(MAX_BURN_AMOUNT))
+    }
+
+    // Ptokens the wallet can still buy
+    fn wallet_allowance(&self, stats: &UserStats) -> u64 {
+        self.max_wallet_ptokens
+            .map_or(u64::MAX, |max| max.saturating_sub(stats.ptokens_bought))
+    }
+
+    fn burn_cooled_down(&self, stats: &UserStats, slot: u64) -> bool {
+        match self.burn_cooldown {
+            Some(cooldown) => {
+                stats.last_burn_slot == 0 || slot >= stats.last_burn_slot.saturating_add(cooldown)
+            }
+            None => true,
+        }
+    }
+}
+
+#[account]
+// Per user and pool counters behind the pool limits, PDA of the pool and user
+pub struct UserStats {
+    prob_pool: Pubkey,
+    user: Pubkey,
+    // Ptokens bought from the pool or listings less those sold back
+    ptokens_bought: u64,
+    // Slot of the last burn request, zero before the first
+    last_burn_slot: u64,
+    // Ptokens bought from the pool not yet sold back, the most a sale frees up
+    pool_ptokens: u64,
+}
+
+impl UserStats {
+    pub const LEN: usize = 8 + 2 * 32 + 3 * 8;
+
+    fn record_bought(&mut self, prob_pool: Pubkey, user: Pubkey, ptokens: u64) -> Result<()> {
+        self.prob_pool = prob_pool;
+        self.user = user;
+        self.ptokens_bought = self
+            .ptokens_bought
+            .checked_add(ptokens)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[account]
+// Burn committed to a future randomness reveal
+pub struct BurnRequest {
+    user: Pubkey,
+    prob_pool: Pubkey,
+    amount: u64,
+    fee_paid: u64,
+    // Part of fee_paid that went to the pool creator
+    creator_fee: u64,
+    status: BurnStatus,
+    target_slot: u64,
+    // Default pubkey settles with the target slot hash
+    oracle: Pubkey,
+    seed: [u8; 32],
+    mode: BurnMode,
+    // Receipt, one draw per ticket in multi ticket mode, at most MAX_BURN_AMOUNT
+    draws: Vec<u64>,
+    won: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum BurnStatus {
+    // Fresh record, never requested
+    Unused,
+    Pending,
+    Settled,
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum BurnMode {
+    // One draw over the whole burn amount
+    Single,
+    // An independent draw per ptoken, stops at the first win
+    MultiTicket,
+}
+
+#[account]
+// Ptokens escrowed by a seller, PDA of the pool and seller
+pub struct Listing {
+    seller: Pubkey,
+    prob_pool: Pubkey,
+    ptoken_amount: u64,
+    // Ask and bids are for the whole listing in quote base units
+    ask: u64,
+    // Best bid held in the bid vault, zero without bids
+    bid: u64,
+    bidder: Pubkey,
+}
+
+impl Listing {
+    pub const LEN: usize = 8 + 2 * 32 + 3 * 8 + 32;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+// Raffle tickets first_ticket..first_ticket + tickets
+pub struct TicketRange {
+    first_ticket: u64,
+    tickets: u64,
+}
+
+#[account]
+// Raffle tickets bought by a user, PDA of the pool and user
+pub struct Entry {
+    prob_pool: Pubkey,
+    user: Pubkey,
+    tickets: u64,
+    ranges: Vec<TicketRange>,
+}
+
+impl Entry {
+    pub const LEN: usize = 8 + 2 * 32 + 8 + 4 + MAX_ENTRY_RANGES * 16;
+
+    // Record a purchase, extending the last range when it follows on from it
+    fn add(&mut self, first_ticket: u64, tickets: u64) -> Result<()> {
+        self.tickets = self
+            .tickets
+            .checked_add(tickets)
+            .ok_or(error::FortuneError::MathOverflow)?;
+        match self.ranges.last_mut() {
+            Some(range) if range.first_ticket + range.tickets == first_ticket => {
+                range.tickets += tickets;
+            }
+            _ => {
+                require!(
+                    self.ranges.len() < MAX_ENTRY_RANGES,
+                    error::FortuneError::EntryLimit
+                );
+                self.ranges.push(TicketRange {
+                    first_ticket,
+                    tickets,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn holds(&self, ticket: u64) -> bool {
+        self.ranges.iter().any(|range| {
+            ticket >= range.first_ticket && ticket - range.first_ticket < range.tickets
+        })
+    }
+}
+
+#[account]
+// Raffle draw committed to a future randomness reveal
+pub struct Draw {
+    prob_pool: Pubkey,
+    status: BurnStatus,
+    target_slot: u64,
+    // Default pubkey settles with the target slot hash
+    oracle: Pubkey,
+    seed: [u8; 32],
+    ticket: u64,
+    winner: Pubkey,
 }
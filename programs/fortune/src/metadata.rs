@@ -0,0 +1,168 @@
+use crate::error::FortuneError;
+use anchor_lang::prelude::*;
+use std::convert::TryInto;
+
+// Metaplex token metadata program
+pub mod token_metadata {
+    anchor_lang::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+// Account key of a MetadataV1 account
+const METADATA_V1: u8 = 4;
+// Creator entry: address, verified, share
+const CREATOR_LEN: usize = 34;
+
+// Collection named by a mint's metadata, None when the metadata is missing or the
+// collection authority has not verified the mint
+pub fn verified_collection(metadata: &AccountInfo, mint: &Pubkey) -> Result<Option<Pubkey>> {
+    let (address, _) = Pubkey::find_program_address(
+        &[b"metadata", token_metadata::ID.as_ref(), mint.as_ref()],
+        &token_metadata::ID,
+    );
+    require!(metadata.key() == address, FortuneError::InvalidMetadata);
+    // Only the metadata program can create an account at its PDA
+    if metadata.owner != &token_metadata::ID {
+        return Ok(None);
+    }
+    let data = metadata.try_borrow_data()?;
+    Ok(parse_collection(&data, mint))
+}
+
+// Borsh layout of MetadataV1 read up to the collection field. Parsed by hand to avoid
+// depending on the metadata program crate, older accounts without the field have none.
+fn parse_collection(data: &[u8], mint: &Pubkey) -> Option<Pubkey> {
+    let mut reader = Reader { data, offset: 0 };
+    if reader.take(1)?[0] != METADATA_V1 {
+        return None;
+    }
+    // Update authority
+    reader.take(32)?;
+    if reader.take(32)? != mint.as_ref() {
+        return None;
+    }
+    // Name, symbol and uri
+    for _ in 0..3 {
+        let len = reader.u32()? as usize;
+        reader.take(len)?;
+    }
+    // Seller fee basis points
+    reader.take(2)?;
+    if reader.option()? {
+        let creators = reader.u32()? as usize;
+        reader.take(creators.checked_mul(CREATOR_LEN)?)?;
+    }
+    // Primary sale happened and is mutable
+    reader.take(2)?;
+    // Edition nonce
+    if reader.option()? {
+        reader.take(1)?;
+    }
+    // Token standard
+    if reader.option()? {
+        reader.take(1)?;
+    }
+    if !reader.option()? {
+        return None;
+    }
+    let verified = reader.take(1)?[0] == 1;
+    let key = Pubkey::new(reader.take(32)?);
+    if verified {
+        Some(key)
+    } else {
+        None
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn option(&mut self) -> Option<bool> {
+        Some(self.take(1)?[0] == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(data: &mut Vec<u8>, value: &str, padded: usize) {
+        data.extend_from_slice(&(padded as u32).to_le_bytes());
+        data.extend_from_slice(value.as_bytes());
+        data.resize(data.len() + padded - value.len(), 0);
+    }
+
+    // Metadata as written by the metadata program, zero padded to its fixed size
+    fn metadata(mint: &Pubkey, creators: usize, collection: Option<(bool, Pubkey)>) -> Vec<u8> {
+        let mut data = vec![METADATA_V1];
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        string(&mut data, "Fortune", 32);
+        string(&mut data, "FTN", 10);
+        string(&mut data, "https://example.com", 200);
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&(creators as u32).to_le_bytes());
+        data.resize(data.len() + creators * CREATOR_LEN, 1);
+        // Primary sale happened, is mutable, edition nonce, token standard
+        data.extend_from_slice(&[0, 1, 1, 255, 1, 0]);
+        match collection {
+            Some((verified, key)) => {
+                data.extend_from_slice(&[1, verified as u8]);
+                data.extend_from_slice(key.as_ref());
+            }
+            None => data.push(0),
+        }
+        data.resize(679, 0);
+        data
+    }
+
+    #[test]
+    fn reads_verified_collection() {
+        let mint = Pubkey::new_unique();
+        let collection = Pubkey::new_unique();
+        let data = metadata(&mint, 2, Some((true, collection)));
+        assert_eq!(parse_collection(&data, &mint), Some(collection));
+    }
+
+    #[test]
+    fn ignores_unverified_collection() {
+        let mint = Pubkey::new_unique();
+        let data = metadata(&mint, 0, Some((false, Pubkey::new_unique())));
+        assert_eq!(parse_collection(&data, &mint), None);
+        let data = metadata(&mint, 1, None);
+        assert_eq!(parse_collection(&data, &mint), None);
+    }
+
+    #[test]
+    fn rejects_other_mint_or_account() {
+        let mint = Pubkey::new_unique();
+        let mut data = metadata(&mint, 1, Some((true, Pubkey::new_unique())));
+        assert_eq!(parse_collection(&data, &Pubkey::new_unique()), None);
+        data[0] = 6;
+        assert_eq!(parse_collection(&data, &mint), None);
+    }
+
+    #[test]
+    fn truncated_metadata_has_no_collection() {
+        let mint = Pubkey::new_unique();
+        let data = metadata(&mint, 3, Some((true, Pubkey::new_unique())));
+        assert_eq!(parse_collection(&data[..300], &mint), None);
+        // Creator count running past the data
+        let mut data = metadata(&mint, 0, None);
+        data[322..326].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(parse_collection(&data, &mint), None);
+    }
+}
@@ -1,7 +1,104 @@
+use crate::error::FortuneError;
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::sysvar::instructions::get_instruction_relative;
+use anchor_lang::solana_program::{ed25519_program, keccak};
 use std::convert::TryInto;
 
+// Source of randomness for a committed request seed
+pub trait RandomnessSource {
+    // None once the source can no longer reveal randomness for the seed
+    fn reveal(&self, seed: [u8; 32]) -> Result<Option<[u8; 32]>>;
+}
+
+// Hash of the slot a request committed to
+pub struct SlotHashSource<'a, 'info> {
+    pub slot_hashes: &'a AccountInfo<'info>,
+    pub target_slot: u64,
+}
+
+impl RandomnessSource for SlotHashSource<'_, '_> {
+    fn reveal(&self, _seed: [u8; 32]) -> Result<Option<[u8; 32]>> {
+        match slot_hash_at(self.slot_hashes, self.target_slot)? {
+            SlotHash::Pending => err!(FortuneError::BurnNotReady),
+            SlotHash::Found(hash) => Ok(Some(hash)),
+            SlotHash::Expired => Ok(None),
+        }
+    }
+}
+
+// Oracle ed25519 signature over the seed, verified by the Ed25519 sigverify
+// instruction placed right before the current one
+pub struct OracleSource<'a, 'info> {
+    pub instructions: &'a AccountInfo<'info>,
+    pub oracle: Pubkey,
+}
+
+impl RandomnessSource for OracleSource<'_, '_> {
+    fn reveal(&self, seed: [u8; 32]) -> Result<Option<[u8; 32]>> {
+        let ix = get_instruction_relative(-1, self.instructions)
+            .map_err(|_| error!(FortuneError::InvalidOracleSignature))?;
+        require!(
+            ix.program_id == ed25519_program::id(),
+            FortuneError::InvalidOracleSignature
+        );
+        let signature = verified_signature(&ix.data, &self.oracle, &seed)
+            .ok_or_else(|| error!(FortuneError::InvalidOracleSignature))?;
+        Ok(Some(keccak::hash(&signature).to_bytes()))
+    }
+}
+
+// Source a request committed to, default oracle pubkey settles with the slot hash
+pub fn source<'a, 'info>(
+    oracle: Pubkey,
+    target_slot: u64,
+    slot_hashes: &'a AccountInfo<'info>,
+    instructions: &'a AccountInfo<'info>,
+) -> Box<dyn RandomnessSource + 'a> {
+    if oracle == Pubkey::default() {
+        Box::new(SlotHashSource {
+            slot_hashes,
+            target_slot,
+        })
+    } else {
+        Box::new(OracleSource {
+            instructions,
+            oracle,
+        })
+    }
+}
+
+// Ed25519 instruction data: signature count, padding, then for each signature the
+// offsets (u16) of signature, pubkey and message with their instruction indexes.
+// Only a single signature with all data inline in the instruction is accepted.
+fn verified_signature(data: &[u8], signer: &Pubkey, message: &[u8]) -> Option<[u8; 64]> {
+    let read_u16 = |offset: usize| -> Option<usize> {
+        Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?) as usize)
+    };
+    if data.len() < 16 || data[0] != 1 {
+        return None;
+    }
+    let signature_offset = read_u16(2)?;
+    let public_key_offset = read_u16(6)?;
+    let message_offset = read_u16(10)?;
+    let message_size = read_u16(12)?;
+    // Instruction indexes must point at this instruction
+    for index_offset in [4, 8, 14] {
+        if read_u16(index_offset)? != u16::MAX as usize {
+            return None;
+        }
+    }
+    let public_key = data.get(public_key_offset..public_key_offset + 32)?;
+    if public_key != signer.as_ref() {
+        return None;
+    }
+    if data.get(message_offset..message_offset + message_size)? != message {
+        return None;
+    }
+    data.get(signature_offset..signature_offset + 64)?
+        .try_into()
+        .ok()
+}
+
 //https://docs.chain.link/docs/chainlink-vrf-best-practices/#getting-multiple-random-number
 pub fn expand_with_num(randomness: [u8; 32], n: u64) -> u64 {
     let mut hasher = keccak::Hasher::default();
@@ -28,6 +125,74 @@ pub fn expand_with_pubkey(randomness: [u8; 32], n: [u8; 32]) -> u64 {
     )
 }
 
+// One draw per ticket, without replacement: ticket i wins when its draw out of
+// supply - i is zero, so the overall odds match a single draw over the same
+// ptokens. Stops at the first winning ticket.
+pub fn draw_tickets(
+    randomness: [u8; 32],
+    seed: [u8; 32],
+    tickets: u64,
+    supply: u64,
+) -> (Vec<u64>, bool) {
+    // Slot hash randomness is shared by every request on the slot
+    let randomness = keccak::hashv(&[&randomness, &seed]).to_bytes();
+    let mut draws = Vec::new();
+    for i in 0..tickets {
+        // The last ticket out of the supply always wins, so this never reaches zero
+        let draw = expand_with_num(randomness, i) % (supply - i);
+        draws.push(draw);
+        if draw == 0 {
+            return (draws, true);
+        }
+    }
+    (draws, false)
+}
+
+// Roll picking which prize a win takes, independent of the draws that decided the win
+pub fn prize_roll(randomness: [u8; 32], seed: [u8; 32]) -> u64 {
+    expand_with_pubkey(keccak::hashv(&[&randomness, b"prize"]).to_bytes(), seed)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SlotHash {
+    // Target slot has not been reached yet
+    Pending,
+    // Hash of the first produced slot at or after the target
+    Found([u8; 32]),
+    // Target slot has been pushed out of the sysvar
+    Expired,
+}
+
+// SlotHashes sysvar data is a u64 length followed by (slot, hash) entries, newest first.
+// Parsed by hand since the full sysvar is too large to deserialize on chain.
+pub fn slot_hash_at(slot_hashes: &AccountInfo, target_slot: u64) -> Result<SlotHash> {
+    let data = slot_hashes.try_borrow_data()?;
+    let len = u64::from_le_bytes(data[0..8].try_into().unwrap()) as usize;
+    let entry = |i: usize| {
+        let offset = 8 + i * 40;
+        let slot = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let hash: [u8; 32] = data[offset + 8..offset + 40].try_into().unwrap();
+        (slot, hash)
+    };
+    if len == 0 || entry(0).0 < target_slot {
+        return Ok(SlotHash::Pending);
+    }
+    // Skipped slots have no entry, so the oldest entry must not be after the target
+    // to prove no earlier produced slot was dropped
+    if entry(len - 1).0 > target_slot {
+        return Ok(SlotHash::Expired);
+    }
+    let mut found = entry(0).1;
+    for i in 0..len {
+        let (slot, hash) = entry(i);
+        if slot < target_slot {
+            break;
+        }
+        found = hash;
+    }
+    Ok(SlotHash::Found(found))
+}
+
 // // https://docs.rs/solana-program/1.8.2/solana_program/sysvar/recent_blockhashes/struct.RecentBlockhashes.html
 // pub fn last_blockhash_accessor(recent_blockhashes: &AccountInfo) -> Result<[u8; 32], ProgramError> {
 //     let bytes = recent_blockhashes.try_borrow_data()?;
@@ -4,10 +4,24 @@ import { Fortune } from '../target/types/fortune';
 import {
   PublicKey, Keypair, SystemProgram, Transaction, TransactionInstruction, LAMPORTS_PER_SOL,
   SYSVAR_RECENT_BLOCKHASHES_PUBKEY, SYSVAR_SLOT_HASHES_PUBKEY,
-  SYSVAR_RENT_PUBKEY
+  SYSVAR_RENT_PUBKEY, SYSVAR_INSTRUCTIONS_PUBKEY, Ed25519Program, sendAndConfirmTransaction
 } from "@solana/web3.js";
-import { TOKEN_PROGRAM_ID, Token, NATIVE_MINT, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { TOKEN_PROGRAM_ID, Token, NATIVE_MINT, ASSOCIATED_TOKEN_PROGRAM_ID, MintLayout } from "@solana/spl-token";
 import { assert } from "chai";
+import * as fs from "fs";
+
+// Metaplex token metadata program
+const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+// Verified collection of the NFT whose metadata is loaded from tests/fixtures
+const VERIFIED_COLLECTION = new PublicKey("5h9CUywDzbr7UKP54r7wDeQz3CnorjdrbLD6yMuF1uSw");
+
+async function metadataAddress(mint: PublicKey): Promise<PublicKey> {
+  const [metadata] = await PublicKey.findProgramAddress(
+    [Buffer.from(anchor.utils.bytes.utf8.encode("metadata")), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+    TOKEN_METADATA_PROGRAM_ID
+  );
+  return metadata
+}
 
 describe('fortune', () => {
 
@@ -16,26 +30,211 @@ describe('fortune', () => {
   anchor.setProvider(anchor.Provider.env());
   const program = anchor.workspace.Fortune as Program<Fortune>;
 
+  async function poolMetadataAddress(pool: PublicKey): Promise<PublicKey> {
+    const [metadata] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("metadata")), pool.toBuffer()],
+      program.programId
+    );
+    return metadata
+  }
+
+  async function lpMintAddress(pool: PublicKey): Promise<PublicKey> {
+    const [mint] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("lp")), pool.toBuffer()],
+      program.programId
+    );
+    return mint
+  }
+
+  async function lpVaultAddress(pool: PublicKey): Promise<PublicKey> {
+    const [vault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("lp_vault")), pool.toBuffer()],
+      program.programId
+    );
+    return vault
+  }
+
+  async function creatorVaultAddress(mint: PublicKey, pool: PublicKey): Promise<PublicKey> {
+    const [vault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("creator")), mint.toBuffer(), pool.toBuffer(), creatorAuth.publicKey.toBuffer()],
+      program.programId
+    );
+    return vault
+  }
+
+  async function userStatsAddress(pool: PublicKey, user: PublicKey): Promise<PublicKey> {
+    const [stats] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("stats")), pool.toBuffer(), user.toBuffer()],
+      program.programId
+    );
+    return stats
+  }
+
+  async function listingAddresses(pool: PublicKey, seller: PublicKey) {
+    const [listing] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("listing")), pool.toBuffer(), seller.toBuffer()],
+      program.programId
+    );
+    const [listingVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("escrow")), listing.toBuffer()],
+      program.programId
+    );
+    const [bidVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("bid")), listing.toBuffer()],
+      program.programId
+    );
+    // Seller's burn request, listings lock while it is pending
+    const [sellerBurnRequest] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("request")), pool.toBuffer(), seller.toBuffer()],
+      program.programId
+    );
+    return { listing, listingVault, bidVault, sellerBurnRequest }
+  }
+
+  async function pda(seeds: Buffer[]): Promise<PublicKey> {
+    const [address] = await PublicKey.findProgramAddress(seeds, program.programId);
+    return address
+  }
+
+  // Creates a day long constant product pool around a fresh NFT, config fields and
+  // accounts can be overridden. The buyer gets a wSOL account funded with quoteLamports.
+  async function createTestPool(configOverrides = {}, options: {
+    prizeAccount?: PublicKey,
+    prizeMint?: PublicKey,
+    accounts?: object,
+    quoteLamports?: number,
+  } = {}) {
+    const pool = Keypair.generate();
+    let nftMint = options.prizeMint
+    let creatorNft = options.prizeAccount
+    if (nftMint === undefined) {
+      const mint = await Token.createMint(
+        provider.connection,
+        mintAuth,
+        mintAuth.publicKey,
+        null,
+        0,
+        TOKEN_PROGRAM_ID
+      );
+      creatorNft = await mint.createAccount(creatorAuth.publicKey);
+      await mint.mintTo(creatorNft, mintAuth.publicKey, [mintAuth], 1);
+      nftMint = mint.publicKey
+    }
+    const buyerQuote = await Token.createWrappedNativeAccount(
+      provider.connection,
+      TOKEN_PROGRAM_ID,
+      buyerAuth.publicKey,
+      buyerAuth,
+      options.quoteLamports ?? 5 * LAMPORTS_PER_SOL
+    );
+    const ptokenMint = await pda([Buffer.from(anchor.utils.bytes.utf8.encode("mint")), pool.publicKey.toBuffer()])
+    const nftVault = await pda([Buffer.from(anchor.utils.bytes.utf8.encode("prize")), nftMint.toBuffer(), pool.publicKey.toBuffer()])
+    const splVault = await pda([Buffer.from(anchor.utils.bytes.utf8.encode("vault")), NATIVE_MINT.toBuffer(), pool.publicKey.toBuffer()])
+    const ptokenVault = await pda([Buffer.from(anchor.utils.bytes.utf8.encode("vault")), ptokenMint.toBuffer(), pool.publicKey.toBuffer()])
+    const userVault = await pda([Buffer.from(anchor.utils.bytes.utf8.encode("vault")), ptokenMint.toBuffer(), buyerAuth.publicKey.toBuffer()])
+    const openUntil = new anchor.BN(Math.floor(Date.now() / 1000) + 24 * 60 * 60)
+    const poolConfig = {
+      lamportAmount: splAmount,
+      ptokenAmount: ptokenAmount,
+      openUntil: openUntil,
+      claimDeadline: null,
+      refundDeadline: openUntil,
+      oddsModel: { remainingSupply: {} },
+      curve: { constantProduct: {} },
+      reserve: new anchor.BN(0),
+      limits: noLimits,
+      mode: { continuous: {} },
+      ticketPrice: new anchor.BN(0),
+      prize: { kind: { nft: {} }, amount: one, weight: one },
+      metadata: poolMetadata,
+      ...configOverrides
+    }
+    await program.rpc.createPool(
+      poolConfig,
+      {
+        accounts: {
+          signer: creatorAuth.publicKey,
+          nftAccount: creatorNft,
+          probPool: pool.publicKey,
+          poolMetadata: await poolMetadataAddress(pool.publicKey),
+          ptokenMint: ptokenMint,
+          nftVault: nftVault,
+          lamportVault: splVault,
+          ptokenVault: ptokenVault,
+          nftMint: nftMint,
+          nftMetadata: await metadataAddress(nftMint),
+          collections: collections,
+          creatorVault: await creatorVaultAddress(NATIVE_MINT, pool.publicKey),
+          creatorSolVault: await creatorVaultAddress(NATIVE_MINT, pool.publicKey),
+          lpMint: await lpMintAddress(pool.publicKey),
+          lpVault: await lpVaultAddress(pool.publicKey),
+          quoteMint: NATIVE_MINT,
+          nativeMint: NATIVE_MINT,
+          state: state,
+          quote: nativeQuote,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY,
+          ...options.accounts
+        },
+        signers: [creatorAuth, pool]
+      });
+    return { pool, config: poolConfig, nftMint, creatorNft, buyerQuote, ptokenMint, nftVault, splVault, ptokenVault, userVault }
+  }
+
   // Auth
   const fortuneAuth = Keypair.generate();
   const creatorAuth = Keypair.generate();
   const buyerAuth = Keypair.generate();
   const mintAuth = Keypair.generate();
+  const oracleAuth = Keypair.generate();
 
   // Params
   const swapFee = new anchor.BN(25);
   const one = new anchor.BN(1);
   const splAmount = new anchor.BN(10 * LAMPORTS_PER_SOL);
   const ptokenAmount = new anchor.BN(10);
-  const buyAmount = new anchor.BN(4);
+  const buyAmount = new anchor.BN(6);
+  const sellAmount = new anchor.BN(1);
   const burnAmount = new anchor.BN(4);
+  const oracleBurnAmount = new anchor.BN(3);
   const withdrawAmount = new anchor.BN(0);
   const burnCost = new anchor.BN(10000)
   const feeScalar = new anchor.BN(1000)
+  const creatorFeeBps = new anchor.BN(2000)
+  const bpsScalar = new anchor.BN(10000)
   const splMin = new anchor.BN(LAMPORTS_PER_SOL * .01)
   const splMax = new anchor.BN(LAMPORTS_PER_SOL * 100000)
   const ptokenMax = new anchor.BN(LAMPORTS_PER_SOL * 1000000)
   const ptokenMin = new anchor.BN(2)
+  const config = {
+    swapFee: swapFee,
+    burnCost: burnCost,
+    feeScalar: feeScalar,
+    ptokenInitMax: ptokenMax,
+    ptokenInitMin: ptokenMin,
+    swapFeeMin: new anchor.BN(10),
+    swapFeeMax: new anchor.BN(100),
+    burnCostMin: new anchor.BN(5000),
+    burnCostMax: new anchor.BN(50000),
+    creatorFeeBps: creatorFeeBps,
+  }
+  const quoteConfig = {
+    lamportInitMin: splMin,
+    lamportInitMax: splMax,
+  }
+  const poolMetadata = {
+    name: "Fortune pool",
+    uri: "https://example.com/pool.json",
+    category: "art",
+    swapFee: null,
+    burnCost: null,
+  }
+  const noLimits = {
+    maxWalletPtokens: null,
+    maxBurn: null,
+    burnCooldown: null,
+  }
 
   // Testing
   let spl_cost = null;
@@ -46,6 +245,7 @@ describe('fortune', () => {
   const userPtokenAccount = Keypair.generate();
   const creatorSplAccount = Keypair.generate();
   const buyerNftAccount = Keypair.generate();
+  const buyerSplAccount = Keypair.generate();
   const creatorNftAccount = Keypair.generate();
   let nftAccount = null;
   let ptokenMint = null;
@@ -56,8 +256,22 @@ describe('fortune', () => {
   let fortuneVault = null;
   let userPtokenVault = null;
   let userBurn = null;
+  let burnRequest = null;
   let userNftVault = null;
   let state = null;
+  let unwrapVault = null;
+  let nativeQuote = null;
+  let collections = null;
+  // Pool priced in an SPL quote mint, also settles burns with the oracle
+  const quotePool = Keypair.generate();
+  let quoteNftMint = null;
+  let quotePtokenMint = null;
+  let quoteNftVault = null;
+  let quotePoolVault = null;
+  let quoteUserVault = null;
+  let quoteUserBurn = null;
+  let quoteBurnRequest = null;
+  let buyerQuoteAccount = null;
 
   // Bumps
   let ptokenMintBump = null;
@@ -67,8 +281,12 @@ describe('fortune', () => {
   let fortuneVaultBump = null;
   let userPtokenVaultBump = null;
   let userBurnBump = null;
+  let burnRequestBump = null;
   let userNftVaultBump = null;
   let stateBump = null;
+  let unwrapVaultBump = null;
+  let nativeQuoteBump = null;
+  let collectionsBump = null;
 
   it('Initialize state', async () => {
     // Airdrop to creator auth
@@ -84,6 +302,15 @@ describe('fortune', () => {
     const buyerAuthAirdrop = await provider.connection.requestAirdrop(buyerAuth.publicKey, 100 * LAMPORTS_PER_SOL);
     await provider.connection.confirmTransaction(buyerAuthAirdrop);
 
+    // Buyer pays from a wrapped SOL account
+    buyerQuoteAccount = await Token.createWrappedNativeAccount(
+      provider.connection,
+      TOKEN_PROGRAM_ID,
+      buyerAuth.publicKey,
+      buyerAuth,
+      50 * LAMPORTS_PER_SOL
+    );
+
     // Nft mint
     nftMint = await Token.createMint(
       provider.connection,
@@ -116,7 +343,7 @@ describe('fortune', () => {
     // nft vault pda
     [nftVault, nftVaultBump] = await PublicKey.findProgramAddress(
       [
-        Buffer.from(anchor.utils.bytes.utf8.encode("vault")),
+        Buffer.from(anchor.utils.bytes.utf8.encode("prize")),
         nftMint.publicKey.toBuffer(),
         probPool.publicKey.toBuffer(),
       ],
@@ -176,6 +403,31 @@ describe('fortune', () => {
       ],
       program.programId
     );
+    // Burn request
+    [burnRequest, burnRequestBump] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("request")),
+        probPool.publicKey.toBuffer(),
+        buyerAuth.publicKey.toBuffer()
+      ],
+      program.programId
+    );
+    // Fee unwrap vault PDA
+    [unwrapVault, unwrapVaultBump] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("unwrap")),
+        NATIVE_MINT.toBuffer(),
+      ],
+      program.programId
+    );
+    // SOL quote mint registration
+    [nativeQuote, nativeQuoteBump] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("quote")),
+        NATIVE_MINT.toBuffer(),
+      ],
+      program.programId
+    );
     // State
     [state, stateBump] = await PublicKey.findProgramAddress(
       [
@@ -183,91 +435,459 @@ describe('fortune', () => {
       ],
       program.programId
     );
+    // Collection allowlist
+    [collections, collectionsBump] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode("collections")),
+      ],
+      program.programId
+    );
   });
 
   it('Initialize program', async () => {
     const tx = await program.rpc.initialize(
-      swapFee,
-      burnCost,
-      feeScalar,
-      splMin,
-      splMax,
-      ptokenMax,
-      ptokenMin,
+      config,
+      quoteConfig,
       {
         accounts: {
           signer: fortuneAuth.publicKey,
           splVault: fortuneVault,
           splMint: NATIVE_MINT,
           state: state,
+          quote: nativeQuote,
+          collections: collections,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY
         },
         signers: [fortuneAuth]
       });
+    // Second initialize cannot overwrite state
+    try {
+      await program.rpc.initialize(
+        config,
+        quoteConfig,
+        {
+          accounts: {
+            signer: creatorAuth.publicKey,
+            splVault: fortuneVault,
+            splMint: NATIVE_MINT,
+            state: state,
+            quote: nativeQuote,
+            collections: collections,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch {
+      let _state = await program.account.state.fetch(state)
+      assert.ok(_state.authority.equals(fortuneAuth.publicKey))
+    }
   });
 
-  it('Create pool', async () => {
-    const tx = await program.rpc.createPool(
-      splAmount,
-      ptokenAmount,
+  it('Update config', async () => {
+    // Swap fee above fee scalar is rejected
+    try {
+      await program.rpc.updateConfig(
+        { ...config, swapFee: feeScalar.add(one) },
+        {
+          accounts: {
+            authority: fortuneAuth.publicKey,
+            state: state,
+          },
+          signers: [fortuneAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Swap fee cannot exceed fee scalar"))
+    }
+    // Default fee outside the range pools pick from is rejected
+    try {
+      await program.rpc.updateConfig(
+        { ...config, swapFeeMin: swapFee.add(one) },
+        {
+          accounts: {
+            authority: fortuneAuth.publicKey,
+            state: state,
+          },
+          signers: [fortuneAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Default fee must be within its bounds"))
+    }
+    // Creator share above the whole fee is rejected
+    try {
+      await program.rpc.updateConfig(
+        { ...config, creatorFeeBps: bpsScalar.add(one) },
+        {
+          accounts: {
+            authority: fortuneAuth.publicKey,
+            state: state,
+          },
+          signers: [fortuneAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Creator fee share cannot exceed 10000 bps"))
+    }
+    // Only the authority can update
+    try {
+      await program.rpc.updateConfig(
+        config,
+        {
+          accounts: {
+            authority: creatorAuth.publicKey,
+            state: state,
+          },
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch {
+      assert.ok(true)
+    }
+    const tx = await program.rpc.updateConfig(
+      { ...config, burnCost: burnCost.add(one) },
       {
         accounts: {
-          signer: creatorAuth.publicKey,
-          nftAccount: nftAccount,
-          probPool: probPool.publicKey,
-          ptokenMint: ptokenMint,
-          nftVault: nftVault,
-          lamportVault: splVault,
-          ptokenVault: ptokenVault,
-          nftMint: nftMint.publicKey,
-          nativeMint: NATIVE_MINT,
+          authority: fortuneAuth.publicKey,
+          state: state,
+        },
+        signers: [fortuneAuth]
+      });
+    let _state = await program.account.state.fetch(state)
+    assert.ok(_state.burnCost.eq(burnCost.add(one)))
+    // Restore config for the rest of the suite
+    await program.rpc.updateConfig(
+      config,
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+        },
+        signers: [fortuneAuth]
+      });
+    // Initial liquidity bounds are set per quote mint
+    const quoteAccounts = {
+      authority: fortuneAuth.publicKey,
+      quoteMint: NATIVE_MINT,
+      quote: nativeQuote,
+      state: state,
+    }
+    try {
+      await program.rpc.updateQuote(
+        { lamportInitMin: splMax, lamportInitMax: splMin },
+        {
+          accounts: quoteAccounts,
+          signers: [fortuneAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Init min must be below init max"))
+    }
+    await program.rpc.updateQuote(
+      { ...quoteConfig, lamportInitMin: splMin.add(one) },
+      {
+        accounts: quoteAccounts,
+        signers: [fortuneAuth]
+      });
+    let _quote = await program.account.quote.fetch(nativeQuote)
+    assert.ok(_quote.lamportInitMin.eq(splMin.add(one)))
+    await program.rpc.updateQuote(
+      quoteConfig,
+      {
+        accounts: quoteAccounts,
+        signers: [fortuneAuth]
+      });
+  });
+
+  it('Rotate authority', async () => {
+    const newAuth = Keypair.generate();
+    await program.rpc.proposeAuthority(
+      newAuth.publicKey,
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+        },
+        signers: [fortuneAuth]
+      });
+    // Authority is unchanged until accepted
+    let _state = await program.account.state.fetch(state)
+    assert.ok(_state.authority.equals(fortuneAuth.publicKey))
+    assert.ok(_state.pendingAuthority.equals(newAuth.publicKey))
+    await program.rpc.acceptAuthority(
+      {
+        accounts: {
+          newAuthority: newAuth.publicKey,
+          state: state,
+        },
+        signers: [newAuth]
+      });
+    _state = await program.account.state.fetch(state)
+    assert.ok(_state.authority.equals(newAuth.publicKey))
+    assert.ok(_state.pendingAuthority.equals(PublicKey.default))
+    // Hand authority back for the rest of the suite
+    await program.rpc.proposeAuthority(
+      fortuneAuth.publicKey,
+      {
+        accounts: {
+          authority: newAuth.publicKey,
+          state: state,
+        },
+        signers: [newAuth]
+      });
+    await program.rpc.acceptAuthority(
+      {
+        accounts: {
+          newAuthority: fortuneAuth.publicKey,
           state: state,
-          systemProgram: SystemProgram.programId,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          rent: SYSVAR_RENT_PUBKEY
         },
+        signers: [fortuneAuth]
+      });
+    _state = await program.account.state.fetch(state)
+    assert.ok(_state.authority.equals(fortuneAuth.publicKey))
+  });
+
+  it('Create pool', async () => {
+    const openUntil = new anchor.BN(Math.floor(Date.now() / 1000) + 24 * 60 * 60)
+    const claimDeadline = openUntil.add(new anchor.BN(24 * 60 * 60))
+    const refundDeadline = openUntil
+    const poolConfig = {
+      lamportAmount: splAmount,
+      ptokenAmount: ptokenAmount,
+      openUntil: openUntil,
+      claimDeadline: claimDeadline,
+      refundDeadline: refundDeadline,
+      oddsModel: { remainingSupply: {} },
+      curve: { constantProduct: {} },
+      reserve: new anchor.BN(0),
+      limits: noLimits,
+      mode: { continuous: {} },
+      ticketPrice: new anchor.BN(0),
+      prize: { kind: { nft: {} }, amount: one, weight: one },
+      metadata: poolMetadata,
+    }
+    const poolAccounts = {
+      signer: creatorAuth.publicKey,
+      nftAccount: nftAccount,
+      probPool: probPool.publicKey,
+      poolMetadata: await poolMetadataAddress(probPool.publicKey),
+      ptokenMint: ptokenMint,
+      nftVault: nftVault,
+      lamportVault: splVault,
+      ptokenVault: ptokenVault,
+      nftMint: nftMint.publicKey,
+      nftMetadata: await metadataAddress(nftMint.publicKey),
+      collections: collections,
+      creatorVault: await creatorVaultAddress(NATIVE_MINT, probPool.publicKey),
+      creatorSolVault: await creatorVaultAddress(NATIVE_MINT, probPool.publicKey),
+      lpMint: await lpMintAddress(probPool.publicKey),
+      lpVault: await lpVaultAddress(probPool.publicKey),
+      quoteMint: NATIVE_MINT,
+      nativeMint: NATIVE_MINT,
+      state: state,
+      quote: nativeQuote,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    }
+    // Winners get at least a day to claim
+    try {
+      await program.rpc.createPool(
+        { ...poolConfig, claimDeadline: openUntil.add(new anchor.BN(60 * 60)) },
+        {
+          accounts: poolAccounts,
+          signers: [creatorAuth, probPool]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Deadline must be in the future"))
+    }
+    const tx = await program.rpc.createPool(
+      poolConfig,
+      {
+        accounts: poolAccounts,
         signers: [creatorAuth, probPool]
       });
     // Pool initialized correctly
     let _pool = await program.account.probPool.fetch(probPool.publicKey)
     assert.ok(_pool.authority.equals(creatorAuth.publicKey))
-    assert.ok(_pool.nftAuthority.equals(creatorAuth.publicKey))
     assert.ok(_pool.lamportVault.equals(splVault))
     assert.ok(_pool.ptokenVault.equals(ptokenVault))
     assert.ok(_pool.ptokenMint.equals(ptokenMint))
-    assert.ok(_pool.nftMint.equals(nftMint.publicKey))
-    assert.ok(_pool.claimed == false)
+    assert.ok(_pool.prizes.length == 1)
+    assert.ok(_pool.prizes[0].mint.equals(nftMint.publicKey))
+    assert.ok(_pool.prizes[0].status.open !== undefined)
     assert.ok(_pool.lamportSupply.eq(splAmount))
     assert.ok(_pool.ptokenSupply.eq(ptokenAmount))
     assert.ok(_pool.outstandingPtokens.toNumber() == 0)
+    assert.ok(_pool.openUntil.eq(openUntil))
+    assert.ok(_pool.claimDeadline.eq(claimDeadline))
+    assert.ok(_pool.refundDeadline.eq(refundDeadline))
+    assert.ok(_pool.oddsModel.remainingSupply !== undefined)
+    assert.ok(_pool.mode.continuous !== undefined)
+    assert.ok(_pool.refunding == false)
+    assert.ok(_pool.swapFee == null)
+    assert.ok(_pool.burnCost == null)
+    let _metadata = await program.account.poolMetadata.fetch(await poolMetadataAddress(probPool.publicKey))
+    assert.ok(_metadata.probPool.equals(probPool.publicKey))
+    assert.ok(_metadata.name == poolMetadata.name)
+    assert.ok(_metadata.uri == poolMetadata.uri)
+    assert.ok(_metadata.category == poolMetadata.category)
     // Set vars for buy testing
     let k = _pool.ptokenSupply.mul(_pool.lamportSupply)
-    let new_lamport_supply = k.div(_pool.ptokenSupply.sub(buyAmount))
+    let new_ptoken_supply = _pool.ptokenSupply.sub(buyAmount)
+    let new_lamport_supply = k.add(new_ptoken_supply).sub(one).div(new_ptoken_supply)
     spl_cost = new_lamport_supply.sub(_pool.lamportSupply)
     spl_fee = ((spl_cost.mul(swapFee)).div(feeScalar))
 
   });
 
+  it('Update pool metadata', async () => {
+    const accounts = {
+      authority: creatorAuth.publicKey,
+      probPool: probPool.publicKey,
+      poolMetadata: await poolMetadataAddress(probPool.publicKey),
+      state: state,
+    }
+    const updated = {
+      name: "Renamed pool",
+      uri: "https://example.com/renamed.json",
+      category: "pfp",
+      swapFee: new anchor.BN(50),
+      burnCost: new anchor.BN(20000),
+    }
+    // Only the pool authority can update
+    try {
+      await program.rpc.updatePoolMetadata(
+        updated,
+        {
+          accounts: { ...accounts, authority: buyerAuth.publicKey },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch {
+      let _metadata = await program.account.poolMetadata.fetch(accounts.poolMetadata)
+      assert.ok(_metadata.name == poolMetadata.name)
+    }
+    // Name longer than 32 bytes
+    try {
+      await program.rpc.updatePoolMetadata(
+        { ...updated, name: "x".repeat(33) },
+        {
+          accounts: accounts,
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Pool metadata field too long"))
+    }
+    // Fees must stay within the protocol bounds
+    try {
+      await program.rpc.updatePoolMetadata(
+        { ...updated, swapFee: config.swapFeeMax.add(one) },
+        {
+          accounts: accounts,
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Pool fee outside protocol bounds"))
+    }
+    try {
+      await program.rpc.updatePoolMetadata(
+        { ...updated, burnCost: config.burnCostMin.sub(one) },
+        {
+          accounts: accounts,
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Pool fee outside protocol bounds"))
+    }
+    await program.rpc.updatePoolMetadata(
+      updated,
+      {
+        accounts: accounts,
+        signers: [creatorAuth]
+      });
+    let _metadata = await program.account.poolMetadata.fetch(accounts.poolMetadata)
+    assert.ok(_metadata.name == updated.name)
+    assert.ok(_metadata.uri == updated.uri)
+    assert.ok(_metadata.category == updated.category)
+    let _pool = await program.account.probPool.fetch(probPool.publicKey)
+    assert.ok(_pool.swapFee.eq(updated.swapFee))
+    assert.ok(_pool.burnCost.eq(updated.burnCost))
+    // Back to the protocol fees the trading tests expect
+    await program.rpc.updatePoolMetadata(
+      poolMetadata,
+      {
+        accounts: accounts,
+        signers: [creatorAuth]
+      });
+    _pool = await program.account.probPool.fetch(probPool.publicKey)
+    assert.ok(_pool.swapFee == null)
+    assert.ok(_pool.burnCost == null)
+  });
+
   it('Buy', async () => {
+    const buyAccounts = {
+      signer: buyerAuth.publicKey,
+      userQuoteAccount: buyerQuoteAccount,
+      poolLamportVault: splVault,
+      poolPtokenVault: ptokenVault,
+      probPool: probPool.publicKey,
+      fortuneLamportVault: fortuneVault,
+      creatorVault: await creatorVaultAddress(NATIVE_MINT, probPool.publicKey),
+      lpVault: await lpVaultAddress(probPool.publicKey),
+      userStats: await userStatsAddress(probPool.publicKey, buyerAuth.publicKey),
+      userPtokenVault: userPtokenVault,
+      ptokenMint: ptokenMint,
+      quoteMint: NATIVE_MINT,
+      state: state,
+      quote: nativeQuote,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Cost above max lamports in is rejected
+    try {
+      await program.rpc.buy(
+        buyAmount,
+        spl_cost.add(spl_fee).sub(one),
+        buyAmount,
+        {
+          accounts: buyAccounts,
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Slippage limit exceeded"))
+    }
+    let _event = null
+    const listener = program.addEventListener("Bought", (event) => { _event = event })
     const tx = await program.rpc.buy(
+      buyAmount,
+      spl_cost.add(spl_fee),
       buyAmount,
       {
-        accounts: {
-          signer: buyerAuth.publicKey,
-          poolLamportVault: splVault,
-          poolPtokenVault: ptokenVault,
-          probPool: probPool.publicKey,
-          fortuneLamportVault: fortuneVault,
-          userPtokenVault: userPtokenVault,
-          ptokenMint: ptokenMint,
-          nativeMint: NATIVE_MINT,
-          state: state,
-          systemProgram: SystemProgram.programId,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          rent: SYSVAR_RENT_PUBKEY
-        },
+        accounts: buyAccounts,
         signers: [buyerAuth]
       });
     // User ptoken vault received tokens
@@ -283,82 +903,520 @@ describe('fortune', () => {
     // Sol sent to pool vault
     let _splBalance = await provider.connection.getTokenAccountBalance(splVault)
     assert.ok(_splBalance.value.amount == spl_cost.toString())
+    // Creator vault received its share of the fee
+    const creatorFee = spl_fee.mul(creatorFeeBps).div(bpsScalar)
+    let _creatorBalance = await provider.connection.getTokenAccountBalance(await creatorVaultAddress(NATIVE_MINT, probPool.publicKey))
+    assert.ok(_creatorBalance.value.amount == creatorFee.toString())
+    // Swap published as an event
+    while (_event == null) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    await program.removeEventListener(listener)
+    assert.ok(_event.ptokenAmount.eq(buyAmount))
+    assert.ok(_event.lamports.eq(spl_cost))
+    assert.ok(_event.fee.eq(spl_fee))
+    assert.ok(_event.creatorFee.eq(creatorFee))
   });
 
-  it('Request Burn', async () => {
-    const tx = await program.rpc.requestBurn(
-      burnAmount,
+  it('Sell', async () => {
+    let _before = await program.account.probPool.fetch(probPool.publicKey)
+    let k = _before.ptokenSupply.mul(_before.lamportSupply)
+    let new_ptoken_supply = _before.ptokenSupply.add(sellAmount)
+    let new_lamport_supply = k.add(new_ptoken_supply).sub(one).div(new_ptoken_supply)
+    let proceeds = _before.lamportSupply.sub(new_lamport_supply)
+    let fee = proceeds.mul(swapFee).div(feeScalar)
+    const tx = await program.rpc.sell(
+      sellAmount,
       {
         accounts: {
           signer: buyerAuth.publicKey,
+          recipient: buyerSplAccount.publicKey,
+          poolLamportVault: splVault,
+          poolPtokenVault: ptokenVault,
+          probPool: probPool.publicKey,
           fortuneLamportVault: fortuneVault,
+          creatorVault: await creatorVaultAddress(NATIVE_MINT, probPool.publicKey),
+          lpVault: await lpVaultAddress(probPool.publicKey),
+          userStats: await userStatsAddress(probPool.publicKey, buyerAuth.publicKey),
           userPtokenVault: userPtokenVault,
-          userBurn: userBurn,
-          probPool: probPool.publicKey,
           ptokenMint: ptokenMint,
+          quoteMint: NATIVE_MINT,
           state: state,
-          nativeMint: NATIVE_MINT,
+          quote: nativeQuote,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY
         },
-        signers: [buyerAuth]
+        signers: [buyerAuth, buyerSplAccount]
       });
-    // User burn receives ptokens
-    let _burnBalance = await provider.connection.getTokenAccountBalance(userBurn)
-    assert.ok(_burnBalance.value.amount == burnAmount.toString())
-    // User ptoken vault sends ptokens
-    let _vaultBalance = await provider.connection.getTokenAccountBalance(userPtokenVault)
-    assert.ok(_vaultBalance.value.amount == buyAmount.sub(burnAmount).toString())
+    // User ptoken vault sent tokens back
+    let _userBalance = await provider.connection.getTokenAccountBalance(userPtokenVault)
+    assert.ok(_userBalance.value.amount == buyAmount.sub(sellAmount).toString())
+    // Seller received proceeds minus fees
+    let _splBalance = await provider.connection.getTokenAccountBalance(buyerSplAccount.publicKey)
+    assert.ok(_splBalance.value.amount == proceeds.sub(fee).toString())
+    // Pool metadata updated
+    let _pool = await program.account.probPool.fetch(probPool.publicKey)
+    assert.ok(_pool.ptokenSupply.eq(new_ptoken_supply))
+    assert.ok(_pool.lamportSupply.eq(new_lamport_supply))
+    assert.ok(_pool.outstandingPtokens.eq(buyAmount.sub(sellAmount)))
   });
 
-  it('User Withdraw', async () => {
-    const tx = await program.rpc.userWithdraw(
-      withdrawAmount,
+  it('Secondary listings', async () => {
+    const listed = new anchor.BN(2)
+    const ask = new anchor.BN(LAMPORTS_PER_SOL / 10)
+    const bid = new anchor.BN(LAMPORTS_PER_SOL / 20)
+    const creatorQuoteAccount = await Token.createWrappedNativeAccount(
+      provider.connection,
+      TOKEN_PROGRAM_ID,
+      creatorAuth.publicKey,
+      creatorAuth,
+      LAMPORTS_PER_SOL
+    );
+    const [creatorPtokenVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), ptokenMint.toBuffer(), creatorAuth.publicKey.toBuffer()],
+      program.programId
+    );
+    let _before = await provider.connection.getTokenAccountBalance(userPtokenVault)
+    // Buyer lists ptokens
+    const buyerListing = await listingAddresses(probPool.publicKey, buyerAuth.publicKey)
+    const listAccounts = {
+      signer: buyerAuth.publicKey,
+      userPtokenVault: userPtokenVault,
+      listing: buyerListing.listing,
+      listingVault: buyerListing.listingVault,
+      bidVault: buyerListing.bidVault,
+      probPool: probPool.publicKey,
+      ptokenMint: ptokenMint,
+      quoteMint: NATIVE_MINT,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    try {
+      await program.rpc.listPtokens(
+        listed,
+        new anchor.BN(0),
+        {
+          accounts: listAccounts,
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Ask cannot be less than zero"))
+    }
+    await program.rpc.listPtokens(
+      listed,
+      ask.mul(new anchor.BN(2)),
+      {
+        accounts: listAccounts,
+        signers: [buyerAuth]
+      });
+    let _escrow = await provider.connection.getTokenAccountBalance(buyerListing.listingVault)
+    assert.ok(_escrow.value.amount == listed.toString())
+    // Only the seller can change the ask
+    try {
+      await program.rpc.updateAsk(
+        one,
+        {
+          accounts: {
+            signer: creatorAuth.publicKey,
+            listing: buyerListing.listing,
+            sellerBurnRequest: buyerListing.sellerBurnRequest,
+          },
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Only seller can change ask"))
+    }
+    await program.rpc.updateAsk(
+      ask,
       {
         accounts: {
           signer: buyerAuth.publicKey,
-          userPtokenVault: userPtokenVault,
-          userAccount: userPtokenAccount.publicKey,
-          ptokenMint: ptokenMint,
-          systemProgram: SystemProgram.programId,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          rent: SYSVAR_RENT_PUBKEY
+          listing: buyerListing.listing,
+          sellerBurnRequest: buyerListing.sellerBurnRequest,
         },
-        signers: [buyerAuth, userPtokenAccount]
+        signers: [buyerAuth]
       });
-    // pTokens sent to user account
-    let _balance = await provider.connection.getTokenAccountBalance(userPtokenAccount.publicKey)
-    assert.ok(_balance.value.amount == withdrawAmount.toString())
-  });
-
+    let _listing = await program.account.listing.fetch(buyerListing.listing)
+    assert.ok(_listing.ask.eq(ask))
+    // Creator bids, bids must beat the best one
+    const bidAccounts = {
+      signer: creatorAuth.publicKey,
+      userQuoteAccount: creatorQuoteAccount,
+      listing: buyerListing.listing,
+      sellerBurnRequest: buyerListing.sellerBurnRequest,
+      bidVault: buyerListing.bidVault,
+      bidRefund: creatorQuoteAccount,
+      probPool: probPool.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await program.rpc.placeBid(
+      bid,
+      {
+        accounts: bidAccounts,
+        signers: [creatorAuth]
+      });
+    try {
+      await program.rpc.placeBid(
+        bid,
+        {
+          accounts: bidAccounts,
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Bid is too low"))
+    }
+    _listing = await program.account.listing.fetch(buyerListing.listing)
+    assert.ok(_listing.bid.eq(bid))
+    assert.ok(_listing.bidder.equals(creatorAuth.publicKey))
+    // Creator fills at the ask instead, the bid is refunded
+    let _sellerQuote = await provider.connection.getTokenAccountBalance(buyerQuoteAccount)
+    const fillAccounts = {
+      signer: creatorAuth.publicKey,
+      userQuoteAccount: creatorQuoteAccount,
+      seller: buyerAuth.publicKey,
+      sellerQuoteAccount: buyerQuoteAccount,
+      userPtokenVault: creatorPtokenVault,
+      userStats: await userStatsAddress(probPool.publicKey, creatorAuth.publicKey),
+      listing: buyerListing.listing,
+      sellerBurnRequest: buyerListing.sellerBurnRequest,
+      listingVault: buyerListing.listingVault,
+      bidVault: buyerListing.bidVault,
+      bidRefund: creatorQuoteAccount,
+      probPool: probPool.publicKey,
+      ptokenMint: ptokenMint,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    try {
+      await program.rpc.fillListing(
+        ask.sub(one),
+        {
+          accounts: fillAccounts,
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Slippage limit exceeded"))
+    }
+    await program.rpc.fillListing(
+      ask,
+      {
+        accounts: fillAccounts,
+        signers: [creatorAuth]
+      });
+    let _creatorPtokens = await provider.connection.getTokenAccountBalance(creatorPtokenVault)
+    assert.ok(_creatorPtokens.value.amount == listed.toString())
+    let _sellerAfter = await provider.connection.getTokenAccountBalance(buyerQuoteAccount)
+    assert.ok(new anchor.BN(_sellerAfter.value.amount).sub(new anchor.BN(_sellerQuote.value.amount)).eq(ask))
+    let _creatorQuote = await provider.connection.getTokenAccountBalance(creatorQuoteAccount)
+    assert.ok(new anchor.BN(_creatorQuote.value.amount).eq(new anchor.BN(LAMPORTS_PER_SOL).sub(ask)))
+    // Listing and escrows closed
+    assert.ok(await provider.connection.getAccountInfo(buyerListing.listing) == null)
+    assert.ok(await provider.connection.getAccountInfo(buyerListing.listingVault) == null)
+    assert.ok(await provider.connection.getAccountInfo(buyerListing.bidVault) == null)
+    // Creator lists them back, buyer's bid is accepted
+    const creatorListing = await listingAddresses(probPool.publicKey, creatorAuth.publicKey)
+    await program.rpc.listPtokens(
+      listed,
+      ask.mul(new anchor.BN(10)),
+      {
+        accounts: {
+          ...listAccounts,
+          signer: creatorAuth.publicKey,
+          userPtokenVault: creatorPtokenVault,
+          listing: creatorListing.listing,
+          listingVault: creatorListing.listingVault,
+          bidVault: creatorListing.bidVault,
+        },
+        signers: [creatorAuth]
+      });
+    const buyerBidAccounts = {
+      signer: buyerAuth.publicKey,
+      userQuoteAccount: buyerQuoteAccount,
+      listing: creatorListing.listing,
+      sellerBurnRequest: creatorListing.sellerBurnRequest,
+      bidVault: creatorListing.bidVault,
+      bidRefund: buyerQuoteAccount,
+      probPool: probPool.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    await program.rpc.placeBid(
+      bid,
+      {
+        accounts: buyerBidAccounts,
+        signers: [buyerAuth]
+      });
+    // Bidder can take the bid back
+    await program.rpc.withdrawBid(
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          recipient: buyerQuoteAccount,
+          listing: creatorListing.listing,
+          bidVault: creatorListing.bidVault,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [buyerAuth]
+      });
+    _listing = await program.account.listing.fetch(creatorListing.listing)
+    assert.ok(_listing.bid.isZero())
+    await program.rpc.placeBid(
+      bid,
+      {
+        accounts: buyerBidAccounts,
+        signers: [buyerAuth]
+      });
+    const acceptAccounts = {
+      signer: creatorAuth.publicKey,
+      recipient: creatorQuoteAccount,
+      bidder: buyerAuth.publicKey,
+      bidderPtokenVault: userPtokenVault,
+      bidderStats: await userStatsAddress(probPool.publicKey, buyerAuth.publicKey),
+      listing: creatorListing.listing,
+      sellerBurnRequest: creatorListing.sellerBurnRequest,
+      listingVault: creatorListing.listingVault,
+      bidVault: creatorListing.bidVault,
+      probPool: probPool.publicKey,
+      ptokenMint: ptokenMint,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Seller sets the least they take, a bid swapped for a lower one is rejected
+    try {
+      await program.rpc.acceptBid(
+        bid.add(one),
+        {
+          accounts: acceptAccounts,
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Slippage limit exceeded"))
+    }
+    await program.rpc.acceptBid(
+      bid,
+      {
+        accounts: acceptAccounts,
+        signers: [creatorAuth]
+      });
+    // Buyer holds the same ptokens again, creator kept the spread
+    let _after = await provider.connection.getTokenAccountBalance(userPtokenVault)
+    assert.ok(_after.value.amount == _before.value.amount)
+    _creatorQuote = await provider.connection.getTokenAccountBalance(creatorQuoteAccount)
+    assert.ok(new anchor.BN(_creatorQuote.value.amount).eq(new anchor.BN(LAMPORTS_PER_SOL).sub(ask).add(bid)))
+    assert.ok(await provider.connection.getAccountInfo(creatorListing.listing) == null)
+    // Pool supply untouched by peer to peer trades
+    let _pool = await program.account.probPool.fetch(probPool.publicKey)
+    assert.ok(_pool.outstandingPtokens.eq(buyAmount.sub(sellAmount)))
+  });
+
+  it('Request Burn', async () => {
+    const requestAccounts = {
+      signer: buyerAuth.publicKey,
+      fortuneLamportVault: fortuneVault,
+      creatorSolVault: await creatorVaultAddress(NATIVE_MINT, probPool.publicKey),
+      userPtokenVault: userPtokenVault,
+      userBurn: userBurn,
+      burnRequest: burnRequest,
+      userStats: await userStatsAddress(probPool.publicKey, buyerAuth.publicKey),
+      probPool: probPool.publicKey,
+      ptokenMint: ptokenMint,
+      state: state,
+      quote: nativeQuote,
+      nativeMint: NATIVE_MINT,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Burns above the limit are rejected
+    try {
+      await program.rpc.requestBurn(
+        new anchor.BN(10),
+        { single: {} },
+        {
+          accounts: requestAccounts,
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn amount above the pool's limit"))
+    }
+    const tx = await program.rpc.requestBurn(
+      burnAmount,
+      { single: {} },
+      {
+        accounts: requestAccounts,
+        signers: [buyerAuth]
+      });
+    // User burn receives ptokens
+    let _burnBalance = await provider.connection.getTokenAccountBalance(userBurn)
+    assert.ok(_burnBalance.value.amount == burnAmount.toString())
+    // User ptoken vault sends ptokens
+    let _vaultBalance = await provider.connection.getTokenAccountBalance(userPtokenVault)
+    assert.ok(_vaultBalance.value.amount == buyAmount.sub(sellAmount).sub(burnAmount).toString())
+    // Burn request committed to a future slot
+    let _request = await program.account.burnRequest.fetch(burnRequest)
+    assert.ok(_request.user.equals(buyerAuth.publicKey))
+    assert.ok(_request.amount.eq(burnAmount))
+    assert.ok(_request.status.pending !== undefined)
+    let _state = await program.account.state.fetch(state)
+    assert.ok(_request.feePaid.eq(_state.burnCost))
+    // Only one pending request per user and pool
+    try {
+      await program.rpc.requestBurn(
+        one,
+        { single: {} },
+        {
+          accounts: requestAccounts,
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn requests pending against pool"))
+    }
+    // The seller's listings lock while their burn is pending
+    const listing = await listingAddresses(probPool.publicKey, buyerAuth.publicKey)
+    await program.rpc.listPtokens(
+      one,
+      new anchor.BN(LAMPORTS_PER_SOL),
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          userPtokenVault: userPtokenVault,
+          listing: listing.listing,
+          listingVault: listing.listingVault,
+          bidVault: listing.bidVault,
+          probPool: probPool.publicKey,
+          ptokenMint: ptokenMint,
+          quoteMint: NATIVE_MINT,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [buyerAuth]
+      });
+    try {
+      await program.rpc.placeBid(
+        one,
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            userQuoteAccount: buyerQuoteAccount,
+            listing: listing.listing,
+            sellerBurnRequest: listing.sellerBurnRequest,
+            bidVault: listing.bidVault,
+            bidRefund: buyerQuoteAccount,
+            probPool: probPool.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Locked listing"))
+    }
+    try {
+      await program.rpc.updateAsk(
+        one,
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            listing: listing.listing,
+            sellerBurnRequest: listing.sellerBurnRequest,
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Locked listing"))
+    }
+    try {
+      await program.rpc.cancelListing(
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            userPtokenVault: userPtokenVault,
+            listing: listing.listing,
+            sellerBurnRequest: listing.sellerBurnRequest,
+            listingVault: listing.listingVault,
+            bidVault: listing.bidVault,
+            bidRefund: buyerQuoteAccount,
+            probPool: probPool.publicKey,
+            ptokenMint: ptokenMint,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Locked listing"))
+    }
+    let _locked = await provider.connection.getTokenAccountBalance(userPtokenVault)
+    assert.ok(_locked.value.amount == buyAmount.sub(sellAmount).sub(burnAmount).sub(one).toString())
+  });
+
+  it('User Withdraw', async () => {
+    const tx = await program.rpc.userWithdraw(
+      withdrawAmount,
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          userPtokenVault: userPtokenVault,
+          userAccount: userPtokenAccount.publicKey,
+          probPool: probPool.publicKey,
+          ptokenMint: ptokenMint,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [buyerAuth, userPtokenAccount]
+      });
+    // pTokens sent to user account
+    let _balance = await provider.connection.getTokenAccountBalance(userPtokenAccount.publicKey)
+    assert.ok(_balance.value.amount == withdrawAmount.toString())
+  });
+
   it('Execute Burn', async () => {
-    // console.log(userBurn.toBase58())
-    // console.log(probPool.publicKey.toBase58())
-    // console.log(nftVault.toBase58())
-    // console.log(userNftVault.toBase58())
-    // console.log(buyerAuth.publicKey.toBase58())
-    // console.log(nftMint.publicKey.toBase58())
-    // console.log(carolineAuth.publicKey.toBase58())
-    // console.log(ptokenMint.toBase58())
+    // Wait for the committed slot hash
+    let _request = await program.account.burnRequest.fetch(burnRequest)
+    while (await provider.connection.getSlot() <= _request.targetSlot.toNumber()) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    // Outcome is published as an event
+    let _event = null
+    const listener = program.addEventListener("BurnExecuted", (event) => { _event = event })
+    // Any keeper can settle, fee payer is the provider wallet
     const tx = await program.rpc.executeBurn(
-      burnAmount,
       {
         accounts: {
-          fortuneAuthority: fortuneAuth.publicKey,
           user: buyerAuth.publicKey,
-          nftVault: nftVault,
+          burnRequest: burnRequest,
           userBurn: userBurn,
           probPool: probPool.publicKey,
-          nftMint: nftMint.publicKey,
           ptokenMint: ptokenMint,
-          state: state,
           slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY
         },
-        signers: [fortuneAuth]
       });
     // pTokens burnt
     let _balance = await provider.connection.getTokenAccountBalance(userBurn)
@@ -366,6 +1424,61 @@ describe('fortune', () => {
     // Outstanding ptokens updated
     let _pool = await program.account.probPool.fetch(probPool.publicKey);
     assert.ok(_pool.outstandingPtokens.eq(ptokenAmount.sub(burnAmount)).toString())
+    // Burn request settled
+    _request = await program.account.burnRequest.fetch(burnRequest)
+    assert.ok(_request.status.settled !== undefined)
+    // Seller's listing unlocks once the burn settles
+    const listing = await listingAddresses(probPool.publicKey, buyerAuth.publicKey)
+    await program.rpc.cancelListing(
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          userPtokenVault: userPtokenVault,
+          listing: listing.listing,
+          sellerBurnRequest: listing.sellerBurnRequest,
+          listingVault: listing.listingVault,
+          bidVault: listing.bidVault,
+          bidRefund: buyerQuoteAccount,
+          probPool: probPool.publicKey,
+          ptokenMint: ptokenMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [buyerAuth]
+      });
+    let _cancelled = await provider.connection.getTokenAccountBalance(userPtokenVault)
+    assert.ok(_cancelled.value.amount == buyAmount.sub(sellAmount).sub(burnAmount).toString())
+    // A settled request cannot be executed again
+    try {
+      await program.rpc.executeBurn(
+        {
+          accounts: {
+            user: buyerAuth.publicKey,
+            burnRequest: burnRequest,
+            userBurn: userBurn,
+            probPool: probPool.publicKey,
+            ptokenMint: ptokenMint,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("No pending burn request"))
+    }
+    while (_event == null) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    await program.removeEventListener(listener)
+    assert.ok(_event.probPool.equals(probPool.publicKey))
+    assert.ok(_event.user.equals(buyerAuth.publicKey))
+    assert.ok(_event.ptokenAmount.eq(burnAmount))
+    assert.ok(_event.won == (_pool.prizes[0].status.won !== undefined))
+    assert.ok(_event.draws.length == 1)
+    assert.ok(_event.draws[0].lt(_event.denominator))
   });
 
   it('User claim nft', async () => {
@@ -391,7 +1504,7 @@ describe('fortune', () => {
     assert.ok(_nftBalance.value.amount == '0')
     // Pool updated
     let _pool = await program.account.probPool.fetch(probPool.publicKey)
-    assert.ok(_pool.claimed == true)
+    assert.ok(_pool.prizes[0].status.claimed !== undefined)
   });
 
   it('Close pool', async () => {
@@ -402,12 +1515,14 @@ describe('fortune', () => {
           recipient: creatorSplAccount.publicKey,
           nftAccount: creatorNftAccount.publicKey,
           probPool: probPool.publicKey,
+          poolMetadata: await poolMetadataAddress(probPool.publicKey),
           ptokenMint: ptokenMint,
           nftVault: nftVault,
           poolLamportVault: splVault,
           poolPtokenVault: ptokenVault,
+          lpVault: await lpVaultAddress(probPool.publicKey),
           nftMint: nftMint.publicKey,
-          nativeMint: NATIVE_MINT,
+          quoteMint: NATIVE_MINT,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY
@@ -421,5 +1536,2154 @@ describe('fortune', () => {
     catch {
       assert.ok(true)
     }
+    // Metadata closed with the pool
+    assert.ok(await provider.connection.getAccountInfo(await poolMetadataAddress(probPool.publicKey)) == null)
+  });
+
+  it('Withdraw creator fees', async () => {
+    const creatorVault = await creatorVaultAddress(NATIVE_MINT, probPool.publicKey)
+    let _vaultBalance = await provider.connection.getTokenAccountBalance(creatorVault)
+    const earned = new anchor.BN(_vaultBalance.value.amount)
+    assert.ok(earned.gt(new anchor.BN(0)))
+    const half = earned.div(new anchor.BN(2))
+    const feeRecipient = await Token.createWrappedNativeAccount(
+      provider.connection,
+      TOKEN_PROGRAM_ID,
+      creatorAuth.publicKey,
+      creatorAuth,
+      0
+    );
+    const withdrawAccounts = {
+      authority: creatorAuth.publicKey,
+      recipient: feeRecipient,
+      creatorVault: creatorVault,
+      probPool: probPool.publicKey,
+      mint: NATIVE_MINT,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    // Only the pool authority can withdraw
+    try {
+      await program.rpc.withdrawCreatorFees(
+        half,
+        {
+          accounts: { ...withdrawAccounts, authority: buyerAuth.publicKey },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch {
+      assert.ok(true)
+    }
+    // More than the vault holds is rejected
+    try {
+      await program.rpc.withdrawCreatorFees(
+        earned.add(one),
+        {
+          accounts: withdrawAccounts,
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Not enough fees in vault"))
+    }
+    let _event = null
+    const listener = program.addEventListener("CreatorFeesWithdrawn", (event) => { _event = event })
+    await program.rpc.withdrawCreatorFees(
+      half,
+      {
+        accounts: withdrawAccounts,
+        signers: [creatorAuth]
+      });
+    let _balance = await provider.connection.getTokenAccountBalance(feeRecipient)
+    assert.ok(_balance.value.amount == half.toString())
+    while (_event == null) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    await program.removeEventListener(listener)
+    assert.ok(_event.amount.eq(half))
+    assert.ok(_event.authority.equals(creatorAuth.publicKey))
+    // Pool is closed, emptying the vault closes it
+    await program.rpc.withdrawCreatorFees(
+      earned.sub(half),
+      {
+        accounts: withdrawAccounts,
+        signers: [creatorAuth]
+      });
+    _balance = await provider.connection.getTokenAccountBalance(feeRecipient)
+    assert.ok(_balance.value.amount == earned.toString())
+    assert.ok(await provider.connection.getAccountInfo(creatorVault) == null)
+  });
+
+  it('Refund pool without a winner', async () => {
+    const refundPool = Keypair.generate();
+    const refundSplAccount = Keypair.generate();
+    const closeSplAccount = Keypair.generate();
+    const closeNftAccount = Keypair.generate();
+    // Fresh prize for the creator
+    const refundNftMint = await Token.createMint(
+      provider.connection,
+      mintAuth,
+      mintAuth.publicKey,
+      null,
+      0,
+      TOKEN_PROGRAM_ID
+    );
+    const creatorRefundNft = await refundNftMint.createAccount(creatorAuth.publicKey);
+    await refundNftMint.mintTo(creatorRefundNft, mintAuth.publicKey, [mintAuth], 1);
+    // Pool PDAs
+    const [refundPtokenMint] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("mint")), refundPool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [refundNftVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("prize")), refundNftMint.publicKey.toBuffer(), refundPool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [refundSplVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), NATIVE_MINT.toBuffer(), refundPool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [refundPtokenVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), refundPtokenMint.toBuffer(), refundPool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [refundUserVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), refundPtokenMint.toBuffer(), buyerAuth.publicKey.toBuffer()],
+      program.programId
+    );
+    // Pool expires a few seconds out, refunds open right after
+    const refundDeadline = new anchor.BN(Math.floor(Date.now() / 1000) + 3)
+    await program.rpc.createPool(
+      {
+        lamportAmount: splAmount,
+        ptokenAmount: ptokenAmount,
+        openUntil: refundDeadline,
+        claimDeadline: null,
+        refundDeadline: refundDeadline,
+        oddsModel: { remainingSupply: {} },
+        curve: { constantProduct: {} },
+        reserve: new anchor.BN(0),
+        limits: noLimits,
+        mode: { continuous: {} },
+        ticketPrice: new anchor.BN(0),
+        prize: { kind: { nft: {} }, amount: one, weight: one },
+        metadata: poolMetadata,
+      },
+      {
+        accounts: {
+          signer: creatorAuth.publicKey,
+          nftAccount: creatorRefundNft,
+          probPool: refundPool.publicKey,
+          poolMetadata: await poolMetadataAddress(refundPool.publicKey),
+          ptokenMint: refundPtokenMint,
+          nftVault: refundNftVault,
+          lamportVault: refundSplVault,
+          ptokenVault: refundPtokenVault,
+          nftMint: refundNftMint.publicKey,
+          nftMetadata: await metadataAddress(refundNftMint.publicKey),
+          collections: collections,
+          creatorVault: await creatorVaultAddress(NATIVE_MINT, refundPool.publicKey),
+          creatorSolVault: await creatorVaultAddress(NATIVE_MINT, refundPool.publicKey),
+          lpMint: await lpMintAddress(refundPool.publicKey),
+          lpVault: await lpVaultAddress(refundPool.publicKey),
+          quoteMint: NATIVE_MINT,
+          nativeMint: NATIVE_MINT,
+          state: state,
+          quote: nativeQuote,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [creatorAuth, refundPool]
+      });
+    const refundBuyAccounts = {
+      signer: buyerAuth.publicKey,
+      userQuoteAccount: buyerQuoteAccount,
+      poolLamportVault: refundSplVault,
+      poolPtokenVault: refundPtokenVault,
+      probPool: refundPool.publicKey,
+      fortuneLamportVault: fortuneVault,
+      creatorVault: await creatorVaultAddress(NATIVE_MINT, refundPool.publicKey),
+      lpVault: await lpVaultAddress(refundPool.publicKey),
+      userStats: await userStatsAddress(refundPool.publicKey, buyerAuth.publicKey),
+      userPtokenVault: refundUserVault,
+      ptokenMint: refundPtokenMint,
+      quoteMint: NATIVE_MINT,
+      state: state,
+      quote: nativeQuote,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    await program.rpc.buy(
+      buyAmount,
+      new anchor.BN(100 * LAMPORTS_PER_SOL),
+      one,
+      {
+        accounts: refundBuyAccounts,
+        signers: [buyerAuth]
+      });
+    const startAccounts = {
+      signer: buyerAuth.publicKey,
+      nftAccount: creatorRefundNft,
+      probPool: refundPool.publicKey,
+      nftVault: refundNftVault,
+      poolLamportVault: refundSplVault,
+      lpVault: await lpVaultAddress(refundPool.publicKey),
+      nftMint: refundNftMint.publicKey,
+      quoteMint: NATIVE_MINT,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    // Refund cannot start before the deadline
+    try {
+      await program.rpc.startRefund({
+        accounts: startAccounts,
+        signers: [buyerAuth]
+      });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Refund deadline not reached"))
+    }
+    // Wait for the cluster clock to pass the deadline
+    while (await provider.connection.getBlockTime(await provider.connection.getSlot()) <= refundDeadline.toNumber()) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    // Expired pool no longer trades
+    try {
+      await program.rpc.buy(
+        one,
+        new anchor.BN(100 * LAMPORTS_PER_SOL),
+        one,
+        {
+          accounts: refundBuyAccounts,
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Pool is no longer open"))
+    }
+    // Anyone can start the refund
+    await program.rpc.startRefund({
+      accounts: startAccounts,
+      signers: [buyerAuth]
+    });
+    // Creator got the NFT back
+    let _nftBalance = await provider.connection.getTokenAccountBalance(creatorRefundNft)
+    assert.ok(_nftBalance.value.amount == '1')
+    let _pool = await program.account.probPool.fetch(refundPool.publicKey)
+    assert.ok(_pool.refunding == true)
+    assert.ok(_pool.refundPtokens.eq(buyAmount))
+    let _splBalance = await provider.connection.getTokenAccountBalance(refundSplVault)
+    assert.ok(_pool.refundLamports.toString() == _splBalance.value.amount)
+    // Trading is closed while refunding
+    try {
+      await program.rpc.buy(
+        one,
+        new anchor.BN(100 * LAMPORTS_PER_SOL),
+        one,
+        {
+          accounts: refundBuyAccounts,
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Prob pool is closed"))
+    }
+    // Ptokens withdrawn from the vault redeem from the holder's own account
+    const withdrawnAccount = Keypair.generate();
+    const withdrawn = new anchor.BN(2)
+    await program.rpc.userWithdraw(
+      withdrawn,
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          userPtokenVault: refundUserVault,
+          userAccount: withdrawnAccount.publicKey,
+          probPool: refundPool.publicKey,
+          ptokenMint: refundPtokenMint,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [buyerAuth, withdrawnAccount]
+      });
+    const redeemAccounts = {
+      signer: buyerAuth.publicKey,
+      recipient: refundSplAccount.publicKey,
+      ptokenAccount: withdrawnAccount.publicKey,
+      probPool: refundPool.publicKey,
+      ptokenMint: refundPtokenMint,
+      poolLamportVault: refundSplVault,
+      quoteMint: NATIVE_MINT,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Someone else's account cannot be redeemed
+    try {
+      await program.rpc.redeem(
+        withdrawn,
+        {
+          accounts: { ...redeemAccounts, signer: creatorAuth.publicKey },
+          signers: [creatorAuth, refundSplAccount]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Ptoken account is not the signer's"))
+    }
+    await program.rpc.redeem(
+      withdrawn,
+      {
+        accounts: redeemAccounts,
+        signers: [buyerAuth, refundSplAccount]
+      });
+    let _withdrawn = await provider.connection.getTokenAccountBalance(withdrawnAccount.publicKey)
+    assert.ok(_withdrawn.value.amount == '0')
+    // Holder redeems the rest from the vault, every ptoken gets the same rate
+    const tx = await program.rpc.redeem(
+      buyAmount.sub(withdrawn),
+      {
+        accounts: { ...redeemAccounts, ptokenAccount: refundUserVault },
+        signers: [buyerAuth]
+      });
+    const refundRate = (amount: anchor.BN) => amount.mul(_pool.refundLamports).div(_pool.refundPtokens)
+    let _refund = await provider.connection.getTokenAccountBalance(refundSplAccount.publicKey)
+    assert.ok(_refund.value.amount == refundRate(withdrawn).add(refundRate(buyAmount.sub(withdrawn))).toString())
+    _pool = await program.account.probPool.fetch(refundPool.publicKey)
+    assert.ok(_pool.outstandingPtokens.toNumber() == 0)
+    // Pool closes once redemptions finish
+    await program.rpc.closePool(
+      {
+        accounts: {
+          signer: creatorAuth.publicKey,
+          recipient: closeSplAccount.publicKey,
+          nftAccount: closeNftAccount.publicKey,
+          probPool: refundPool.publicKey,
+          poolMetadata: await poolMetadataAddress(refundPool.publicKey),
+          ptokenMint: refundPtokenMint,
+          nftVault: refundNftVault,
+          poolLamportVault: refundSplVault,
+          poolPtokenVault: refundPtokenVault,
+          lpVault: await lpVaultAddress(refundPool.publicKey),
+          nftMint: refundNftMint.publicKey,
+          quoteMint: NATIVE_MINT,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [creatorAuth, closeSplAccount, closeNftAccount]
+      });
+    assert.ok(await provider.connection.getAccountInfo(refundPool.publicKey) == null)
+  });
+
+  it('Reserve price', async () => {
+    // Trading closes in a few seconds, the regular refund deadline is a day later
+    const reserve = new anchor.BN(100 * LAMPORTS_PER_SOL)
+    const openUntil = new anchor.BN(Math.floor(Date.now() / 1000) + 5)
+    const {
+      pool: reservePool,
+      nftMint: reserveNftMint,
+      creatorNft: creatorReserveNft,
+      buyerQuote: reserveQuote,
+      ptokenMint: reservePtokenMint,
+      nftVault: reserveNftVault,
+      splVault: reserveSplVault,
+      ptokenVault: reservePtokenVault,
+      userVault: reserveUserVault,
+    } = await createTestPool({
+      openUntil: openUntil,
+      refundDeadline: openUntil.add(new anchor.BN(24 * 60 * 60)),
+      reserve: reserve,
+    })
+    let _pool = await program.account.probPool.fetch(reservePool.publicKey)
+    assert.ok(_pool.reserve.eq(reserve))
+    await program.rpc.buy(
+      one,
+      new anchor.BN(5 * LAMPORTS_PER_SOL),
+      one,
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          userQuoteAccount: reserveQuote,
+          poolLamportVault: reserveSplVault,
+          poolPtokenVault: reservePtokenVault,
+          probPool: reservePool.publicKey,
+          fortuneLamportVault: fortuneVault,
+          creatorVault: await creatorVaultAddress(NATIVE_MINT, reservePool.publicKey),
+          lpVault: await lpVaultAddress(reservePool.publicKey),
+          userStats: await userStatsAddress(reservePool.publicKey, buyerAuth.publicKey),
+          userPtokenVault: reserveUserVault,
+          ptokenMint: reservePtokenMint,
+          quoteMint: NATIVE_MINT,
+          state: state,
+          quote: nativeQuote,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [buyerAuth]
+      });
+    // Deposits would count towards the reserve
+    const reserveLpMint = new Token(provider.connection, await lpMintAddress(reservePool.publicKey), TOKEN_PROGRAM_ID, mintAuth);
+    try {
+      await program.rpc.addLiquidity(
+        one,
+        one,
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            userQuoteAccount: reserveQuote,
+            lpAccount: await reserveLpMint.createAccount(buyerAuth.publicKey),
+            poolLamportVault: reserveSplVault,
+            poolPtokenVault: reservePtokenVault,
+            lpVault: await lpVaultAddress(reservePool.publicKey),
+            probPool: reservePool.publicKey,
+            ptokenMint: reservePtokenMint,
+            lpMint: reserveLpMint.publicKey,
+            quoteMint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Pools with a reserve do not take liquidity"))
+    }
+    // No burn can win the prize below the reserve
+    try {
+      await program.rpc.requestBurn(
+        one,
+        { single: {} },
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            fortuneLamportVault: fortuneVault,
+            creatorSolVault: await creatorVaultAddress(NATIVE_MINT, reservePool.publicKey),
+            userPtokenVault: reserveUserVault,
+            userBurn: await pda([Buffer.from(anchor.utils.bytes.utf8.encode("burn")), reservePool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()]),
+            burnRequest: await pda([Buffer.from(anchor.utils.bytes.utf8.encode("request")), reservePool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()]),
+            userStats: await userStatsAddress(reservePool.publicKey, buyerAuth.publicKey),
+            probPool: reservePool.publicKey,
+            ptokenMint: reservePtokenMint,
+            state: state,
+            quote: nativeQuote,
+            nativeMint: NATIVE_MINT,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Pool has not collected its reserve"))
+    }
+    const startAccounts = {
+      signer: buyerAuth.publicKey,
+      nftAccount: creatorReserveNft,
+      probPool: reservePool.publicKey,
+      nftVault: reserveNftVault,
+      poolLamportVault: reserveSplVault,
+      lpVault: await lpVaultAddress(reservePool.publicKey),
+      nftMint: reserveNftMint,
+      quoteMint: NATIVE_MINT,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    // Refund waits for the pool to close
+    try {
+      await program.rpc.startRefund({
+        accounts: startAccounts,
+        signers: [buyerAuth]
+      });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Refund deadline not reached"))
+    }
+    while (await provider.connection.getBlockTime(await provider.connection.getSlot()) <= openUntil.toNumber()) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    // Reserve missed, holders are refunded without waiting for the refund deadline
+    await program.rpc.startRefund({
+      accounts: startAccounts,
+      signers: [buyerAuth]
+    });
+    let _nftBalance = await provider.connection.getTokenAccountBalance(creatorReserveNft)
+    assert.ok(_nftBalance.value.amount == '1')
+    _pool = await program.account.probPool.fetch(reservePool.publicKey)
+    assert.ok(_pool.refunding == true)
+    assert.ok(_pool.refundPtokens.eq(one))
+    let _splBalance = await provider.connection.getTokenAccountBalance(reserveSplVault)
+    assert.ok(_pool.refundLamports.toString() == _splBalance.value.amount)
+  });
+
+  it('Wallet limits', async () => {
+    const limitSplAccount = Keypair.generate();
+    const maxWalletPtokens = new anchor.BN(3)
+    const {
+      pool: limitPool,
+      buyerQuote: limitQuote,
+      ptokenMint: limitPtokenMint,
+      splVault: limitSplVault,
+      ptokenVault: limitPtokenVault,
+      userVault: limitUserVault,
+    } = await createTestPool({
+      limits: {
+        maxWalletPtokens: maxWalletPtokens,
+        maxBurn: one,
+        burnCooldown: new anchor.BN(1000),
+      },
+    }, { quoteLamports: 10 * LAMPORTS_PER_SOL })
+    const limitStats = await userStatsAddress(limitPool.publicKey, buyerAuth.publicKey)
+    const limitAccounts = {
+      poolLamportVault: limitSplVault,
+      poolPtokenVault: limitPtokenVault,
+      probPool: limitPool.publicKey,
+      fortuneLamportVault: fortuneVault,
+      creatorVault: await creatorVaultAddress(NATIVE_MINT, limitPool.publicKey),
+      lpVault: await lpVaultAddress(limitPool.publicKey),
+      userPtokenVault: limitUserVault,
+      userStats: limitStats,
+      ptokenMint: limitPtokenMint,
+      quoteMint: NATIVE_MINT,
+      state: state,
+      quote: nativeQuote,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    const limitBuy = async (amount: anchor.BN) => {
+      await program.rpc.buy(
+        amount,
+        new anchor.BN(10 * LAMPORTS_PER_SOL),
+        one,
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            userQuoteAccount: limitQuote,
+            ...limitAccounts
+          },
+          signers: [buyerAuth]
+        });
+    }
+    // Fills stop at the wallet limit
+    await limitBuy(new anchor.BN(5))
+    let _userBalance = await provider.connection.getTokenAccountBalance(limitUserVault)
+    assert.ok(_userBalance.value.amount == maxWalletPtokens.toString())
+    let _stats = await program.account.userStats.fetch(limitStats)
+    assert.ok(_stats.ptokensBought.eq(maxWalletPtokens))
+    try {
+      await limitBuy(one)
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Wallet bought the most ptokens the pool allows"))
+    }
+    // Selling back frees up the allowance
+    await program.rpc.sell(
+      one,
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          recipient: limitSplAccount.publicKey,
+          ...limitAccounts
+        },
+        signers: [buyerAuth, limitSplAccount]
+      });
+    _stats = await program.account.userStats.fetch(limitStats)
+    assert.ok(_stats.ptokensBought.eq(maxWalletPtokens.sub(one)))
+    assert.ok(_stats.poolPtokens.eq(maxWalletPtokens.sub(one)))
+    const requestBurn = async (amount: anchor.BN) => {
+      await program.rpc.requestBurn(
+        amount,
+        { single: {} },
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            fortuneLamportVault: fortuneVault,
+            creatorSolVault: await creatorVaultAddress(NATIVE_MINT, limitPool.publicKey),
+            userPtokenVault: limitUserVault,
+            userBurn: await pda([Buffer.from(anchor.utils.bytes.utf8.encode("burn")), limitPool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()]),
+            burnRequest: await pda([Buffer.from(anchor.utils.bytes.utf8.encode("request")), limitPool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()]),
+            userStats: limitStats,
+            probPool: limitPool.publicKey,
+            ptokenMint: limitPtokenMint,
+            state: state,
+            quote: nativeQuote,
+            nativeMint: NATIVE_MINT,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [buyerAuth]
+        });
+    }
+    // Pool caps each burn below the protocol limit
+    try {
+      await requestBurn(new anchor.BN(2))
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn amount above the pool's limit"))
+    }
+    await requestBurn(one)
+    _stats = await program.account.userStats.fetch(limitStats)
+    assert.ok(_stats.lastBurnSlot.toNumber() > 0)
+    // Next burn waits out the cooldown
+    try {
+      await requestBurn(one)
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn cooldown has not elapsed"))
+    }
+  });
+
+  it('Raffle pool', async () => {
+    const rafflePool = Keypair.generate();
+    const ticketPrice = new anchor.BN(LAMPORTS_PER_SOL / 10)
+    const raffleNftMint = await Token.createMint(
+      provider.connection,
+      mintAuth,
+      mintAuth.publicKey,
+      null,
+      0,
+      TOKEN_PROGRAM_ID
+    );
+    const creatorRaffleNft = await raffleNftMint.createAccount(creatorAuth.publicKey);
+    await raffleNftMint.mintTo(creatorRaffleNft, mintAuth.publicKey, [mintAuth], 1);
+    // Second ticket buyer
+    const mintAuthQuoteAccount = await Token.createWrappedNativeAccount(
+      provider.connection,
+      TOKEN_PROGRAM_ID,
+      mintAuth.publicKey,
+      mintAuth,
+      10 * LAMPORTS_PER_SOL
+    );
+    // Pool PDAs
+    const [rafflePtokenMint] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("mint")), rafflePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [raffleNftVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("prize")), raffleNftMint.publicKey.toBuffer(), rafflePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [raffleSplVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), NATIVE_MINT.toBuffer(), rafflePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [rafflePtokenVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), rafflePtokenMint.toBuffer(), rafflePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [raffleDraw] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("draw")), rafflePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const ticketAccount = Keypair.generate();
+    const entryAddress = async (user: PublicKey) => {
+      const [entry] = await PublicKey.findProgramAddress(
+        [Buffer.from(anchor.utils.bytes.utf8.encode("entry")), rafflePool.publicKey.toBuffer(), user.toBuffer()],
+        program.programId
+      );
+      return entry
+    }
+    const userVaultAddress = async (user: PublicKey) => {
+      const [vault] = await PublicKey.findProgramAddress(
+        [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), rafflePtokenMint.toBuffer(), user.toBuffer()],
+        program.programId
+      );
+      return vault
+    }
+    const openUntil = new anchor.BN(Math.floor(Date.now() / 1000) + 24 * 60 * 60)
+    await program.rpc.createPool(
+      {
+        lamportAmount: splAmount,
+        ptokenAmount: ptokenAmount,
+        openUntil: openUntil,
+        claimDeadline: null,
+        refundDeadline: openUntil,
+        oddsModel: { fixedSupply: {} },
+        curve: { constantProduct: {} },
+        reserve: new anchor.BN(0),
+        limits: noLimits,
+        mode: { raffle: {} },
+        ticketPrice: ticketPrice,
+        prize: { kind: { nft: {} }, amount: one, weight: one },
+        metadata: poolMetadata,
+      },
+      {
+        accounts: {
+          signer: creatorAuth.publicKey,
+          nftAccount: creatorRaffleNft,
+          probPool: rafflePool.publicKey,
+          poolMetadata: await poolMetadataAddress(rafflePool.publicKey),
+          ptokenMint: rafflePtokenMint,
+          nftVault: raffleNftVault,
+          lamportVault: raffleSplVault,
+          ptokenVault: rafflePtokenVault,
+          nftMint: raffleNftMint.publicKey,
+          nftMetadata: await metadataAddress(raffleNftMint.publicKey),
+          collections: collections,
+          creatorVault: await creatorVaultAddress(NATIVE_MINT, rafflePool.publicKey),
+          creatorSolVault: await creatorVaultAddress(NATIVE_MINT, rafflePool.publicKey),
+          lpMint: await lpMintAddress(rafflePool.publicKey),
+          lpVault: await lpVaultAddress(rafflePool.publicKey),
+          quoteMint: NATIVE_MINT,
+          nativeMint: NATIVE_MINT,
+          state: state,
+          quote: nativeQuote,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [creatorAuth, rafflePool]
+      });
+    let _pool = await program.account.probPool.fetch(rafflePool.publicKey)
+    assert.ok(_pool.mode.raffle !== undefined)
+    assert.ok(_pool.ticketPrice.eq(ticketPrice))
+    // Tickets are not traded on the AMM
+    try {
+      await program.rpc.buy(
+        one,
+        new anchor.BN(100 * LAMPORTS_PER_SOL),
+        one,
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            userQuoteAccount: buyerQuoteAccount,
+            poolLamportVault: raffleSplVault,
+            poolPtokenVault: rafflePtokenVault,
+            probPool: rafflePool.publicKey,
+            fortuneLamportVault: fortuneVault,
+            creatorVault: await creatorVaultAddress(NATIVE_MINT, rafflePool.publicKey),
+            lpVault: await lpVaultAddress(rafflePool.publicKey),
+            userStats: await userStatsAddress(rafflePool.publicKey, buyerAuth.publicKey),
+            userPtokenVault: await userVaultAddress(buyerAuth.publicKey),
+            ptokenMint: rafflePtokenMint,
+            quoteMint: NATIVE_MINT,
+            state: state,
+            quote: nativeQuote,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Not available in this pool mode"))
+    }
+    const buyTickets = async (user: Keypair, userQuoteAccount: PublicKey, amount: anchor.BN) => {
+      await program.rpc.buyTickets(
+        amount,
+        new anchor.BN(100 * LAMPORTS_PER_SOL),
+        one,
+        {
+          accounts: {
+            signer: user.publicKey,
+            userQuoteAccount: userQuoteAccount,
+            entry: await entryAddress(user.publicKey),
+            poolLamportVault: raffleSplVault,
+            poolPtokenVault: rafflePtokenVault,
+            probPool: rafflePool.publicKey,
+            fortuneLamportVault: fortuneVault,
+            creatorVault: await creatorVaultAddress(NATIVE_MINT, rafflePool.publicKey),
+            userPtokenVault: await userVaultAddress(user.publicKey),
+            userStats: await userStatsAddress(rafflePool.publicKey, user.publicKey),
+            ptokenMint: rafflePtokenMint,
+            quoteMint: NATIVE_MINT,
+            state: state,
+            quote: nativeQuote,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [user]
+        });
+    }
+    const drawAccounts = {
+      signer: buyerAuth.publicKey,
+      draw: raffleDraw,
+      probPool: rafflePool.publicKey,
+      state: state,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    await buyTickets(buyerAuth, buyerQuoteAccount, new anchor.BN(4))
+    // Draw waits for the raffle to sell out or close
+    try {
+      await program.rpc.requestDraw({
+        accounts: drawAccounts,
+        signers: [buyerAuth]
+      });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Raffle is still selling tickets"))
+    }
+    // One entry per user, purchases that follow on share a ticket range
+    await buyTickets(mintAuth, mintAuthQuoteAccount, new anchor.BN(2))
+    await buyTickets(buyerAuth, buyerQuoteAccount, one)
+    await buyTickets(buyerAuth, buyerQuoteAccount, one)
+    let _entry = await program.account.entry.fetch(await entryAddress(buyerAuth.publicKey))
+    assert.ok(_entry.user.equals(buyerAuth.publicKey))
+    assert.ok(_entry.tickets.toNumber() == 6)
+    assert.ok(_entry.ranges.length == 2)
+    assert.ok(_entry.ranges[0].firstTicket.toNumber() == 0)
+    assert.ok(_entry.ranges[0].tickets.toNumber() == 4)
+    assert.ok(_entry.ranges[1].firstTicket.toNumber() == 6)
+    assert.ok(_entry.ranges[1].tickets.toNumber() == 2)
+    // Tickets cannot leave the buyer's vault before the draw
+    try {
+      await program.rpc.userWithdraw(
+        one,
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            userPtokenVault: await userVaultAddress(buyerAuth.publicKey),
+            userAccount: ticketAccount.publicKey,
+            probPool: rafflePool.publicKey,
+            ptokenMint: rafflePtokenMint,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [buyerAuth, ticketAccount]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Raffle tickets cannot move before the draw"))
+    }
+    // Last buyer gets whatever is left
+    await buyTickets(mintAuth, mintAuthQuoteAccount, ptokenAmount)
+    _entry = await program.account.entry.fetch(await entryAddress(mintAuth.publicKey))
+    assert.ok(_entry.user.equals(mintAuth.publicKey))
+    assert.ok(_entry.tickets.toNumber() == 4)
+    assert.ok(_entry.ranges.length == 2)
+    assert.ok(_entry.ranges[1].firstTicket.toNumber() == 8)
+    _pool = await program.account.probPool.fetch(rafflePool.publicKey)
+    assert.ok(_pool.ptokenSupply.toNumber() == 0)
+    assert.ok(_pool.entries.toNumber() == 2)
+    let _splBalance = await provider.connection.getTokenAccountBalance(raffleSplVault)
+    assert.ok(_splBalance.value.amount == ticketPrice.mul(ptokenAmount).toString())
+    // Sold out
+    try {
+      await buyTickets(buyerAuth, buyerQuoteAccount, one)
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("No more pTokens left to buy"))
+    }
+    // Anyone can request the draw
+    await program.rpc.requestDraw({
+      accounts: drawAccounts,
+      signers: [buyerAuth]
+    });
+    let _draw = await program.account.draw.fetch(raffleDraw)
+    assert.ok(_draw.status.pending !== undefined)
+    // A slot hash draw cannot be committed again
+    try {
+      await program.rpc.requestDraw({
+        accounts: drawAccounts,
+        signers: [buyerAuth]
+      });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn requests pending against pool"))
+    }
+    while (await provider.connection.getSlot() <= _draw.targetSlot.toNumber()) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    // Only the entry holding the winning ticket settles the draw
+    let winner = null
+    for (const user of [buyerAuth, mintAuth]) {
+      try {
+        await program.rpc.executeDraw({
+          accounts: {
+            draw: raffleDraw,
+            entry: await entryAddress(user.publicKey),
+            probPool: rafflePool.publicKey,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          },
+        });
+        winner = user
+        break
+      }
+      catch (err) {
+        assert.ok(err.toString().includes("Entry does not hold the winning ticket"))
+      }
+    }
+    assert.ok(winner != null)
+    _draw = await program.account.draw.fetch(raffleDraw)
+    assert.ok(_draw.status.settled !== undefined)
+    assert.ok(_draw.winner.equals(winner.publicKey))
+    assert.ok(_draw.ticket.lt(ptokenAmount))
+    _pool = await program.account.probPool.fetch(rafflePool.publicKey)
+    assert.ok(_pool.prizes[0].status.won !== undefined)
+    assert.ok(_pool.prizes[0].winner.equals(winner.publicKey))
+    // Winner claims through the usual flow
+    const winnerNftAccount = Keypair.generate();
+    await program.rpc.claimAsset(
+      {
+        accounts: {
+          signer: winner.publicKey,
+          nftAccount: winnerNftAccount.publicKey,
+          probPool: rafflePool.publicKey,
+          nftVault: raffleNftVault,
+          nftMint: raffleNftMint.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [winner, winnerNftAccount]
+      });
+    let _nftBalance = await provider.connection.getTokenAccountBalance(winnerNftAccount.publicKey)
+    assert.ok(_nftBalance.value.amount == '1')
+    // Creator closes with tickets still outstanding and collects the sales
+    const closeSplAccount = Keypair.generate();
+    const closeNftAccount = Keypair.generate();
+    await program.rpc.closePool(
+      {
+        accounts: {
+          signer: creatorAuth.publicKey,
+          recipient: closeSplAccount.publicKey,
+          nftAccount: closeNftAccount.publicKey,
+          probPool: rafflePool.publicKey,
+          poolMetadata: await poolMetadataAddress(rafflePool.publicKey),
+          ptokenMint: rafflePtokenMint,
+          nftVault: raffleNftVault,
+          poolLamportVault: raffleSplVault,
+          poolPtokenVault: rafflePtokenVault,
+          lpVault: await lpVaultAddress(rafflePool.publicKey),
+          nftMint: raffleNftMint.publicKey,
+          quoteMint: NATIVE_MINT,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [creatorAuth, closeSplAccount, closeNftAccount]
+      });
+    let _proceeds = await provider.connection.getTokenAccountBalance(closeSplAccount.publicKey)
+    assert.ok(_proceeds.value.amount == ticketPrice.mul(ptokenAmount).toString())
+  });
+
+  it('Pool with several prizes', async () => {
+    const prizePool = Keypair.generate();
+    // An NFT, a fungible token amount and a SOL bounty
+    const tokenPrizeAmount = new anchor.BN(5 * 10 ** 6)
+    const solPrizeAmount = new anchor.BN(LAMPORTS_PER_SOL)
+    const prizeNftMint = await Token.createMint(
+      provider.connection,
+      mintAuth,
+      mintAuth.publicKey,
+      null,
+      0,
+      TOKEN_PROGRAM_ID
+    );
+    const creatorPrizeNft = await prizeNftMint.createAccount(creatorAuth.publicKey);
+    await prizeNftMint.mintTo(creatorPrizeNft, mintAuth.publicKey, [mintAuth], 1);
+    const prizeTokenMint = await Token.createMint(
+      provider.connection,
+      mintAuth,
+      mintAuth.publicKey,
+      null,
+      6,
+      TOKEN_PROGRAM_ID
+    );
+    const creatorPrizeToken = await prizeTokenMint.createAccount(creatorAuth.publicKey);
+    await prizeTokenMint.mintTo(creatorPrizeToken, mintAuth.publicKey, [mintAuth], tokenPrizeAmount.toNumber());
+    const creatorWsol = await Token.createWrappedNativeAccount(
+      provider.connection,
+      TOKEN_PROGRAM_ID,
+      creatorAuth.publicKey,
+      creatorAuth,
+      0
+    );
+    const prizeVault = async (mint: PublicKey) => {
+      const [vault] = await PublicKey.findProgramAddress(
+        [Buffer.from(anchor.utils.bytes.utf8.encode("prize")), mint.toBuffer(), prizePool.publicKey.toBuffer()],
+        program.programId
+      );
+      return vault
+    }
+    const nftPrizeVault = await prizeVault(prizeNftMint.publicKey)
+    const tokenPrizeVault = await prizeVault(prizeTokenMint.publicKey)
+    const solPrizeVault = await prizeVault(NATIVE_MINT)
+    const [prizePtokenMint] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("mint")), prizePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [prizeSplVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), NATIVE_MINT.toBuffer(), prizePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [prizePtokenVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), prizePtokenMint.toBuffer(), prizePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const openUntil = new anchor.BN(Math.floor(Date.now() / 1000) + 24 * 60 * 60)
+    await program.rpc.createPool(
+      {
+        lamportAmount: splAmount,
+        ptokenAmount: ptokenAmount,
+        openUntil: openUntil,
+        claimDeadline: null,
+        refundDeadline: openUntil,
+        oddsModel: { remainingSupply: {} },
+        curve: { constantProduct: {} },
+        reserve: new anchor.BN(0),
+        limits: noLimits,
+        mode: { continuous: {} },
+        ticketPrice: new anchor.BN(0),
+        prize: { kind: { nft: {} }, amount: one, weight: one },
+        metadata: poolMetadata,
+      },
+      {
+        accounts: {
+          signer: creatorAuth.publicKey,
+          nftAccount: creatorPrizeNft,
+          probPool: prizePool.publicKey,
+          poolMetadata: await poolMetadataAddress(prizePool.publicKey),
+          ptokenMint: prizePtokenMint,
+          nftVault: nftPrizeVault,
+          lamportVault: prizeSplVault,
+          ptokenVault: prizePtokenVault,
+          nftMint: prizeNftMint.publicKey,
+          nftMetadata: await metadataAddress(prizeNftMint.publicKey),
+          collections: collections,
+          creatorVault: await creatorVaultAddress(NATIVE_MINT, prizePool.publicKey),
+          creatorSolVault: await creatorVaultAddress(NATIVE_MINT, prizePool.publicKey),
+          lpMint: await lpMintAddress(prizePool.publicKey),
+          lpVault: await lpVaultAddress(prizePool.publicKey),
+          quoteMint: NATIVE_MINT,
+          nativeMint: NATIVE_MINT,
+          state: state,
+          quote: nativeQuote,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [creatorAuth, prizePool]
+      });
+    const addPrizeAccounts = async (nftAccount: PublicKey, nftVault: PublicKey, nftMint: PublicKey) => ({
+      signer: creatorAuth.publicKey,
+      nftAccount: nftAccount,
+      probPool: prizePool.publicKey,
+      nftVault: nftVault,
+      nftMint: nftMint,
+      nftMetadata: await metadataAddress(nftMint),
+      collections: collections,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    })
+    // A fungible mint is not an NFT
+    try {
+      await program.rpc.addPrize(
+        { kind: { nft: {} }, amount: one, weight: one },
+        {
+          accounts: await addPrizeAccounts(creatorPrizeToken, tokenPrizeVault, prizeTokenMint.publicKey),
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Prize does not match its kind"))
+    }
+    // Token prize three times as likely to be won as the NFT
+    await program.rpc.addPrize(
+      { kind: { token: {} }, amount: tokenPrizeAmount, weight: new anchor.BN(3) },
+      {
+        accounts: await addPrizeAccounts(creatorPrizeToken, tokenPrizeVault, prizeTokenMint.publicKey),
+        signers: [creatorAuth]
+      });
+    // SOL bounty is paid from the creator's lamports
+    await program.rpc.addPrize(
+      { kind: { sol: {} }, amount: solPrizeAmount, weight: one },
+      {
+        accounts: await addPrizeAccounts(creatorWsol, solPrizeVault, NATIVE_MINT),
+        signers: [creatorAuth]
+      });
+    let _pool = await program.account.probPool.fetch(prizePool.publicKey)
+    assert.ok(_pool.prizes.length == 3)
+    assert.ok(_pool.prizes[1].kind.token !== undefined)
+    assert.ok(_pool.prizes[1].amount.eq(tokenPrizeAmount))
+    assert.ok(_pool.prizes[1].weight.toNumber() == 3)
+    assert.ok(_pool.prizes[2].kind.sol !== undefined)
+    assert.ok(_pool.prizes.every((prize) => prize.status.open !== undefined))
+    let _vaultBalance = await provider.connection.getTokenAccountBalance(tokenPrizeVault)
+    assert.ok(_vaultBalance.value.amount == tokenPrizeAmount.toString())
+    _vaultBalance = await provider.connection.getTokenAccountBalance(solPrizeVault)
+    assert.ok(_vaultBalance.value.amount == solPrizeAmount.toString())
+    // A prize nobody won cannot be claimed
+    const claimTokenAccount = Keypair.generate();
+    try {
+      await program.rpc.claimAsset(
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            nftAccount: claimTokenAccount.publicKey,
+            probPool: prizePool.publicKey,
+            nftVault: tokenPrizeVault,
+            nftMint: prizeTokenMint.publicKey,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [buyerAuth, claimTokenAccount]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("No active claim on this pool"))
+    }
+    // SOL bounties are claimed as lamports, without a token account
+    try {
+      await program.rpc.claimSol(
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            probPool: prizePool.publicKey,
+            prizeVault: solPrizeVault,
+            nativeMint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("No active claim on this pool"))
+    }
+    const closeSplAccount = Keypair.generate();
+    const closeAccounts = {
+      signer: creatorAuth.publicKey,
+      recipient: closeSplAccount.publicKey,
+      nftAccount: creatorPrizeNft,
+      probPool: prizePool.publicKey,
+      poolMetadata: await poolMetadataAddress(prizePool.publicKey),
+      ptokenMint: prizePtokenMint,
+      nftVault: nftPrizeVault,
+      poolLamportVault: prizeSplVault,
+      poolPtokenVault: prizePtokenVault,
+      lpVault: await lpVaultAddress(prizePool.publicKey),
+      nftMint: prizeNftMint.publicKey,
+      quoteMint: NATIVE_MINT,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Every extra prize vault has to be passed
+    try {
+      await program.rpc.closePool(
+        {
+          accounts: closeAccounts,
+          remainingAccounts: [
+            { pubkey: tokenPrizeVault, isWritable: true, isSigner: false },
+            { pubkey: creatorPrizeToken, isWritable: true, isSigner: false },
+          ],
+          signers: [creatorAuth, closeSplAccount]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Prize vault accounts missing or out of order"))
+    }
+    // Close returns every prize to the creator, the SOL bounty as lamports
+    const _creatorLamports = await provider.connection.getBalance(creatorAuth.publicKey)
+    await program.rpc.closePool(
+      {
+        accounts: closeAccounts,
+        remainingAccounts: [
+          { pubkey: tokenPrizeVault, isWritable: true, isSigner: false },
+          { pubkey: creatorPrizeToken, isWritable: true, isSigner: false },
+          { pubkey: solPrizeVault, isWritable: true, isSigner: false },
+          { pubkey: creatorAuth.publicKey, isWritable: false, isSigner: false },
+        ],
+        signers: [creatorAuth, closeSplAccount]
+      });
+    let _balance = await provider.connection.getTokenAccountBalance(creatorPrizeNft)
+    assert.ok(_balance.value.amount == '1')
+    _balance = await provider.connection.getTokenAccountBalance(creatorPrizeToken)
+    assert.ok(_balance.value.amount == tokenPrizeAmount.toString())
+    _balance = await provider.connection.getTokenAccountBalance(creatorWsol)
+    assert.ok(_balance.value.amount == '0')
+    // Bounty on top of the rent refunds, less the new recipient account and fees
+    const _lamportsAfter = await provider.connection.getBalance(creatorAuth.publicKey)
+    assert.ok(_lamportsAfter - _creatorLamports > solPrizeAmount.toNumber() - LAMPORTS_PER_SOL / 100)
+    for (const vault of [nftPrizeVault, tokenPrizeVault, solPrizeVault]) {
+      assert.ok(await provider.connection.getAccountInfo(vault) == null)
+    }
+    assert.ok(await provider.connection.getAccountInfo(prizePool.publicKey) == null)
+  });
+
+  it('Pool priced in an SPL quote mint', async () => {
+    // Stablecoin style quote mint
+    const quoteMint = await Token.createMint(
+      provider.connection,
+      mintAuth,
+      mintAuth.publicKey,
+      null,
+      6,
+      TOKEN_PROGRAM_ID
+    );
+    const buyerQuote = await quoteMint.createAccount(buyerAuth.publicKey);
+    await quoteMint.mintTo(buyerQuote, mintAuth.publicKey, [mintAuth], 100 * LAMPORTS_PER_SOL);
+    quoteNftMint = await Token.createMint(
+      provider.connection,
+      mintAuth,
+      mintAuth.publicKey,
+      null,
+      0,
+      TOKEN_PROGRAM_ID
+    );
+    const creatorQuoteNft = await quoteNftMint.createAccount(creatorAuth.publicKey);
+    await quoteNftMint.mintTo(creatorQuoteNft, mintAuth.publicKey, [mintAuth], 1);
+    // PDAs
+    const [quote] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("quote")), quoteMint.publicKey.toBuffer()],
+      program.programId
+    );
+    const [quoteFeeVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), quoteMint.publicKey.toBuffer()],
+      program.programId
+    );
+    [quotePtokenMint] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("mint")), quotePool.publicKey.toBuffer()],
+      program.programId
+    );
+    [quoteNftVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("prize")), quoteNftMint.publicKey.toBuffer(), quotePool.publicKey.toBuffer()],
+      program.programId
+    );
+    [quotePoolVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), quoteMint.publicKey.toBuffer(), quotePool.publicKey.toBuffer()],
+      program.programId
+    );
+    const [quotePtokenVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), quotePtokenMint.toBuffer(), quotePool.publicKey.toBuffer()],
+      program.programId
+    );
+    [quoteUserVault] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault")), quotePtokenMint.toBuffer(), buyerAuth.publicKey.toBuffer()],
+      program.programId
+    );
+    const openUntil = new anchor.BN(Math.floor(Date.now() / 1000) + 24 * 60 * 60)
+    const createAccounts = {
+      signer: creatorAuth.publicKey,
+      nftAccount: creatorQuoteNft,
+      probPool: quotePool.publicKey,
+      poolMetadata: await poolMetadataAddress(quotePool.publicKey),
+      ptokenMint: quotePtokenMint,
+      nftVault: quoteNftVault,
+      lamportVault: quotePoolVault,
+      ptokenVault: quotePtokenVault,
+      nftMint: quoteNftMint.publicKey,
+      nftMetadata: await metadataAddress(quoteNftMint.publicKey),
+      collections: collections,
+      creatorVault: await creatorVaultAddress(quoteMint.publicKey, quotePool.publicKey),
+      creatorSolVault: await creatorVaultAddress(NATIVE_MINT, quotePool.publicKey),
+      lpMint: await lpMintAddress(quotePool.publicKey),
+      lpVault: await lpVaultAddress(quotePool.publicKey),
+      quoteMint: quoteMint.publicKey,
+      nativeMint: NATIVE_MINT,
+      quote: quote,
+      state: state,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Unregistered quote mints are rejected
+    try {
+      await program.rpc.createPool(
+        {
+          lamportAmount: splAmount,
+          ptokenAmount: ptokenAmount,
+          openUntil: openUntil,
+          claimDeadline: null,
+          refundDeadline: openUntil,
+          oddsModel: { fixedSupply: {} },
+          curve: { constantProduct: {} },
+          reserve: new anchor.BN(0),
+          limits: noLimits,
+          mode: { continuous: {} },
+          ticketPrice: new anchor.BN(0),
+        prize: { kind: { nft: {} }, amount: one, weight: one },
+        metadata: poolMetadata,
+        },
+        {
+          accounts: createAccounts,
+          signers: [creatorAuth, quotePool]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("expected this account to be already initialized"))
+    }
+    // Authority registers the mint
+    await program.rpc.addQuoteMint(
+      quoteConfig,
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          fortuneVault: quoteFeeVault,
+          quoteMint: quoteMint.publicKey,
+          quote: quote,
+          state: state,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [fortuneAuth]
+      });
+    await program.rpc.createPool(
+      {
+        lamportAmount: splAmount,
+        ptokenAmount: ptokenAmount,
+        openUntil: openUntil,
+        claimDeadline: null,
+        refundDeadline: openUntil,
+        oddsModel: { fixedSupply: {} },
+        curve: { constantProduct: {} },
+        reserve: new anchor.BN(0),
+        limits: noLimits,
+        mode: { continuous: {} },
+        ticketPrice: new anchor.BN(0),
+        prize: { kind: { nft: {} }, amount: one, weight: one },
+        metadata: poolMetadata,
+      },
+      {
+        accounts: createAccounts,
+        signers: [creatorAuth, quotePool]
+      });
+    let _pool = await program.account.probPool.fetch(quotePool.publicKey)
+    assert.ok(_pool.quoteMint.equals(quoteMint.publicKey))
+    assert.ok(_pool.oddsModel.fixedSupply !== undefined)
+    assert.ok(_pool.initialPtokenSupply.eq(ptokenAmount))
+    // Buy pays with the quote token account
+    let k = _pool.ptokenSupply.mul(_pool.lamportSupply)
+    let new_ptoken_supply = _pool.ptokenSupply.sub(buyAmount)
+    let cost = k.add(new_ptoken_supply).sub(one).div(new_ptoken_supply).sub(_pool.lamportSupply)
+    let fee = cost.mul(swapFee).div(feeScalar)
+    let creatorFee = fee.mul(creatorFeeBps).div(bpsScalar)
+    const quoteBuyAccounts = {
+      signer: buyerAuth.publicKey,
+      userQuoteAccount: buyerQuote,
+      poolLamportVault: quotePoolVault,
+      poolPtokenVault: quotePtokenVault,
+      probPool: quotePool.publicKey,
+      fortuneLamportVault: quoteFeeVault,
+      creatorVault: await creatorVaultAddress(quoteMint.publicKey, quotePool.publicKey),
+      lpVault: await lpVaultAddress(quotePool.publicKey),
+      userStats: await userStatsAddress(quotePool.publicKey, buyerAuth.publicKey),
+      userPtokenVault: quoteUserVault,
+      ptokenMint: quotePtokenMint,
+      quoteMint: quoteMint.publicKey,
+      quote: quote,
+      state: state,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    await program.rpc.buy(
+      buyAmount,
+      cost.add(fee),
+      buyAmount,
+      {
+        accounts: quoteBuyAccounts,
+        signers: [buyerAuth]
+      });
+    let _poolBalance = await provider.connection.getTokenAccountBalance(quotePoolVault)
+    assert.ok(_poolBalance.value.amount == cost.toString())
+    // Fee split between the protocol and the pool creator
+    let _feeBalance = await provider.connection.getTokenAccountBalance(quoteFeeVault)
+    assert.ok(_feeBalance.value.amount == fee.sub(creatorFee).toString())
+    let _creatorBalance = await provider.connection.getTokenAccountBalance(await creatorVaultAddress(quoteMint.publicKey, quotePool.publicKey))
+    assert.ok(_creatorBalance.value.amount == creatorFee.toString())
+    let _quote = await program.account.quote.fetch(quote)
+    assert.ok(_quote.vault.equals(quoteFeeVault))
+    assert.ok(_quote.feesCollected.eq(fee.sub(creatorFee)))
+    let _buyerBalance = await provider.connection.getTokenAccountBalance(buyerQuote)
+    assert.ok(_buyerBalance.value.amount == new anchor.BN(100 * LAMPORTS_PER_SOL).sub(cost).sub(fee).toString())
+    // Liquidity providers deposit at the current price for LP shares
+    const lpMint = new Token(provider.connection, await lpMintAddress(quotePool.publicKey), TOKEN_PROGRAM_ID, mintAuth);
+    const buyerLp = await lpMint.createAccount(buyerAuth.publicKey);
+    const quoteLpVault = await lpVaultAddress(quotePool.publicKey);
+    const liquidityAccounts = {
+      signer: buyerAuth.publicKey,
+      userQuoteAccount: buyerQuote,
+      lpAccount: buyerLp,
+      poolLamportVault: quotePoolVault,
+      poolPtokenVault: quotePtokenVault,
+      lpVault: quoteLpVault,
+      probPool: quotePool.publicKey,
+      ptokenMint: quotePtokenMint,
+      lpMint: lpMint.publicKey,
+      quoteMint: quoteMint.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID
+    };
+    _pool = await program.account.probPool.fetch(quotePool.publicKey)
+    let totalShares = _pool.seedShares.add(_pool.lpShares)
+    const shares = splAmount.mul(totalShares).div(_pool.lamportSupply)
+    let lpPtokens = splAmount.mul(_pool.ptokenSupply).div(_pool.lamportSupply)
+    try {
+      await program.rpc.addLiquidity(
+        splAmount,
+        shares.add(one),
+        {
+          accounts: liquidityAccounts,
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Slippage limit exceeded"))
+    }
+    await program.rpc.addLiquidity(
+      splAmount,
+      shares,
+      {
+        accounts: liquidityAccounts,
+        signers: [buyerAuth]
+      });
+    let _lpPool = await program.account.probPool.fetch(quotePool.publicKey)
+    assert.ok(_lpPool.lpShares.eq(shares))
+    assert.ok(_lpPool.ptokenSupply.eq(_pool.ptokenSupply.add(lpPtokens)))
+    assert.ok(_lpPool.lamportSupply.eq(_pool.lamportSupply.add(splAmount)))
+    let _lpBalance = await provider.connection.getTokenAccountBalance(buyerLp)
+    assert.ok(_lpBalance.value.amount == shares.toString())
+    // Swap fees are shared with providers by shares before the creator cut
+    totalShares = totalShares.add(shares)
+    k = _lpPool.ptokenSupply.mul(_lpPool.lamportSupply)
+    new_ptoken_supply = _lpPool.ptokenSupply.sub(one)
+    let lpCost = k.add(new_ptoken_supply).sub(one).div(new_ptoken_supply).sub(_lpPool.lamportSupply)
+    let lpBuyFee = lpCost.mul(swapFee).div(feeScalar)
+    let lpFee = lpBuyFee.mul(shares).div(totalShares)
+    let lpCreatorFee = lpBuyFee.sub(lpFee).mul(creatorFeeBps).div(bpsScalar)
+    await program.rpc.buy(
+      one,
+      lpCost.add(lpBuyFee),
+      one,
+      {
+        accounts: quoteBuyAccounts,
+        signers: [buyerAuth]
+      });
+    let _lpVaultBalance = await provider.connection.getTokenAccountBalance(quoteLpVault)
+    assert.ok(_lpVaultBalance.value.amount == lpFee.toString())
+    _feeBalance = await provider.connection.getTokenAccountBalance(quoteFeeVault)
+    assert.ok(_feeBalance.value.amount == fee.sub(creatorFee).add(lpBuyFee).sub(lpFee).sub(lpCreatorFee).toString())
+    const removeAccounts = {
+      signer: buyerAuth.publicKey,
+      lpAccount: buyerLp,
+      recipient: buyerQuote,
+      poolLamportVault: quotePoolVault,
+      poolPtokenVault: quotePtokenVault,
+      lpVault: quoteLpVault,
+      probPool: quotePool.publicKey,
+      ptokenMint: quotePtokenMint,
+      lpMint: lpMint.publicKey,
+      quoteMint: quoteMint.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID
+    };
+    // Late deposits buy into the fees already earned, adding and removing in one
+    // transaction takes none of them
+    _lpPool = await program.account.probPool.fetch(quotePool.publicKey)
+    const lateLp = await lpMint.createAccount(buyerAuth.publicKey);
+    let lateShares = splAmount.mul(totalShares).mul(_lpPool.lpShares).div(
+      _lpPool.lamportSupply.mul(_lpPool.lpShares).add(lpFee.mul(totalShares))
+    )
+    const lateFees = lateShares.mul(lpFee).add(_lpPool.lpShares).sub(one).div(_lpPool.lpShares)
+    lateShares = BN.min(lateShares, splAmount.sub(lateFees).mul(totalShares).div(_lpPool.lamportSupply))
+    _buyerBalance = await provider.connection.getTokenAccountBalance(buyerQuote)
+    await provider.send(
+      new Transaction().add(
+        program.instruction.addLiquidity(
+          splAmount,
+          lateShares,
+          { accounts: { ...liquidityAccounts, lpAccount: lateLp } }
+        ),
+        program.instruction.removeLiquidity(
+          lateShares,
+          new anchor.BN(0),
+          { accounts: { ...removeAccounts, lpAccount: lateLp } }
+        )
+      ),
+      [buyerAuth]
+    );
+    let _after = await provider.connection.getTokenAccountBalance(buyerQuote)
+    assert.ok(new anchor.BN(_after.value.amount).lte(new anchor.BN(_buyerBalance.value.amount)))
+    _lpVaultBalance = await provider.connection.getTokenAccountBalance(quoteLpVault)
+    assert.ok(new anchor.BN(_lpVaultBalance.value.amount).gte(lpFee))
+    // Removing every share pays its part of the curve plus the fees earned
+    const lpFees = new anchor.BN(_lpVaultBalance.value.amount)
+    _lpPool = await program.account.probPool.fetch(quotePool.publicKey)
+    let lpLamports = shares.mul(_lpPool.lamportSupply).div(totalShares)
+    lpPtokens = shares.mul(_lpPool.ptokenSupply).div(totalShares)
+    _buyerBalance = await provider.connection.getTokenAccountBalance(buyerQuote)
+    await program.rpc.removeLiquidity(
+      shares,
+      lpLamports.add(lpFees),
+      {
+        accounts: removeAccounts,
+        signers: [buyerAuth]
+      });
+    _after = await provider.connection.getTokenAccountBalance(buyerQuote)
+    assert.ok(_after.value.amount == new anchor.BN(_buyerBalance.value.amount).add(lpLamports).add(lpFees).toString())
+    _pool = await program.account.probPool.fetch(quotePool.publicKey)
+    assert.ok(_pool.lpShares.toNumber() == 0)
+    assert.ok(_pool.ptokenSupply.eq(_lpPool.ptokenSupply.sub(lpPtokens)))
+    assert.ok(_pool.lamportSupply.eq(_lpPool.lamportSupply.sub(lpLamports)))
+    _lpBalance = await provider.connection.getTokenAccountBalance(buyerLp)
+    assert.ok(_lpBalance.value.amount == '0')
+    _lpVaultBalance = await provider.connection.getTokenAccountBalance(quoteLpVault)
+    assert.ok(_lpVaultBalance.value.amount == '0')
+    // Burn accounts for the oracle flow
+    [quoteUserBurn] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("burn")), quotePool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()],
+      program.programId
+    );
+    [quoteBurnRequest] = await PublicKey.findProgramAddress(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("request")), quotePool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()],
+      program.programId
+    );
+  });
+
+  it('Set oracle', async () => {
+    const tx = await program.rpc.setOracle(
+      oracleAuth.publicKey,
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+        },
+        signers: [fortuneAuth]
+      });
+    let _state = await program.account.state.fetch(state)
+    assert.ok(_state.oracle.equals(oracleAuth.publicKey))
+  });
+
+  it('Request oracle burn', async () => {
+    const tx = await program.rpc.requestBurn(
+      oracleBurnAmount,
+      { multiTicket: {} },
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          fortuneLamportVault: fortuneVault,
+          creatorSolVault: await creatorVaultAddress(NATIVE_MINT, quotePool.publicKey),
+          userPtokenVault: quoteUserVault,
+          userBurn: quoteUserBurn,
+          burnRequest: quoteBurnRequest,
+          userStats: await userStatsAddress(quotePool.publicKey, buyerAuth.publicKey),
+          probPool: quotePool.publicKey,
+          ptokenMint: quotePtokenMint,
+          state: state,
+          quote: nativeQuote,
+          nativeMint: NATIVE_MINT,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [buyerAuth]
+      });
+    // Request settles with the oracle
+    let _request = await program.account.burnRequest.fetch(quoteBurnRequest)
+    assert.ok(_request.oracle.equals(oracleAuth.publicKey))
+    // Oracle still has time to settle, pool is open
+    try {
+      await program.rpc.cancelBurn(
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            user: buyerAuth.publicKey,
+            recipient: buyerQuoteAccount,
+            burnRequest: quoteBurnRequest,
+            userBurn: quoteUserBurn,
+            userPtokenVault: quoteUserVault,
+            probPool: quotePool.publicKey,
+            ptokenMint: quotePtokenMint,
+            fortuneLamportVault: fortuneVault,
+            creatorSolVault: await creatorVaultAddress(NATIVE_MINT, quotePool.publicKey),
+            quote: nativeQuote,
+            nativeMint: NATIVE_MINT,
+            systemProgram: SystemProgram.programId,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            rent: SYSVAR_RENT_PUBKEY
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn request cannot be cancelled yet"))
+    }
+  });
+
+  it('Cancel timed out oracle burn', async () => {
+    // Trading closes in a few seconds, long before the oracle request times out
+    const openUntil = new anchor.BN(Math.floor(Date.now() / 1000) + 30)
+    const {
+      pool: stalePool,
+      nftMint: staleNftMint,
+      creatorNft: creatorStaleNft,
+      buyerQuote: staleQuote,
+      ptokenMint: stalePtokenMint,
+      nftVault: staleNftVault,
+      splVault: staleSplVault,
+      ptokenVault: stalePtokenVault,
+      userVault: staleUserVault,
+    } = await createTestPool({
+      openUntil: openUntil,
+      refundDeadline: openUntil,
+    })
+    await program.rpc.buy(
+      one,
+      new anchor.BN(5 * LAMPORTS_PER_SOL),
+      one,
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          userQuoteAccount: staleQuote,
+          poolLamportVault: staleSplVault,
+          poolPtokenVault: stalePtokenVault,
+          probPool: stalePool.publicKey,
+          fortuneLamportVault: fortuneVault,
+          creatorVault: await creatorVaultAddress(NATIVE_MINT, stalePool.publicKey),
+          lpVault: await lpVaultAddress(stalePool.publicKey),
+          userStats: await userStatsAddress(stalePool.publicKey, buyerAuth.publicKey),
+          userPtokenVault: staleUserVault,
+          ptokenMint: stalePtokenMint,
+          quoteMint: NATIVE_MINT,
+          state: state,
+          quote: nativeQuote,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [buyerAuth]
+      });
+    const staleUserBurn = await pda([Buffer.from(anchor.utils.bytes.utf8.encode("burn")), stalePool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()])
+    const staleBurnRequest = await pda([Buffer.from(anchor.utils.bytes.utf8.encode("request")), stalePool.publicKey.toBuffer(), buyerAuth.publicKey.toBuffer()])
+    await program.rpc.requestBurn(
+      one,
+      { single: {} },
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          fortuneLamportVault: fortuneVault,
+          creatorSolVault: await creatorVaultAddress(NATIVE_MINT, stalePool.publicKey),
+          userPtokenVault: staleUserVault,
+          userBurn: staleUserBurn,
+          burnRequest: staleBurnRequest,
+          userStats: await userStatsAddress(stalePool.publicKey, buyerAuth.publicKey),
+          probPool: stalePool.publicKey,
+          ptokenMint: stalePtokenMint,
+          state: state,
+          quote: nativeQuote,
+          nativeMint: NATIVE_MINT,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [buyerAuth]
+      });
+    let _request = await program.account.burnRequest.fetch(staleBurnRequest)
+    assert.ok(_request.oracle.equals(oracleAuth.publicKey))
+    // Owner is gone, anyone else cancels for them
+    const cancelAccounts = {
+      signer: creatorAuth.publicKey,
+      user: buyerAuth.publicKey,
+      recipient: staleQuote,
+      burnRequest: staleBurnRequest,
+      userBurn: staleUserBurn,
+      userPtokenVault: staleUserVault,
+      probPool: stalePool.publicKey,
+      ptokenMint: stalePtokenMint,
+      fortuneLamportVault: fortuneVault,
+      creatorSolVault: await creatorVaultAddress(NATIVE_MINT, stalePool.publicKey),
+      quote: nativeQuote,
+      nativeMint: NATIVE_MINT,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    try {
+      await program.rpc.cancelBurn({
+        accounts: cancelAccounts,
+        signers: [creatorAuth]
+      });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn request cannot be cancelled yet"))
+    }
+    const startAccounts = {
+      signer: creatorAuth.publicKey,
+      nftAccount: creatorStaleNft,
+      probPool: stalePool.publicKey,
+      nftVault: staleNftVault,
+      poolLamportVault: staleSplVault,
+      lpVault: await lpVaultAddress(stalePool.publicKey),
+      nftMint: staleNftMint,
+      quoteMint: NATIVE_MINT,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    // Oracle never settles, the request times out
+    const timeoutSlot = _request.targetSlot.toNumber() + 512
+    while (await provider.connection.getSlot() <= timeoutSlot) {
+      await new Promise(resolve => setTimeout(resolve, 400))
+    }
+    // Refund deadline passed but the pending request holds the pool
+    try {
+      await program.rpc.startRefund({
+        accounts: startAccounts,
+        signers: [creatorAuth]
+      });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Burn requests pending against pool"))
+    }
+    await program.rpc.cancelBurn({
+      accounts: cancelAccounts,
+      signers: [creatorAuth]
+    });
+    _request = await program.account.burnRequest.fetch(staleBurnRequest)
+    assert.ok(_request.status.cancelled !== undefined)
+    // pTokens back with the owner
+    let _balance = await provider.connection.getTokenAccountBalance(staleUserVault)
+    assert.ok(_balance.value.amount == '1')
+    // Nothing left pending, refund starts
+    await program.rpc.startRefund({
+      accounts: startAccounts,
+      signers: [creatorAuth]
+    });
+    let _pool = await program.account.probPool.fetch(stalePool.publicKey)
+    assert.ok(_pool.pendingBurns.isZero())
+    assert.ok(_pool.refunding == true)
+    let _nftBalance = await provider.connection.getTokenAccountBalance(creatorStaleNft)
+    assert.ok(_nftBalance.value.amount == '1')
+  });
+
+  it('Execute oracle burn', async () => {
+    let _request = await program.account.burnRequest.fetch(quoteBurnRequest)
+    const executeAccounts = {
+      user: buyerAuth.publicKey,
+      burnRequest: quoteBurnRequest,
+      userBurn: quoteUserBurn,
+      probPool: quotePool.publicKey,
+      ptokenMint: quotePtokenMint,
+      slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+      instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Signature from any other key is rejected
+    try {
+      await program.rpc.executeBurn({
+        accounts: executeAccounts,
+        instructions: [
+          Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: buyerAuth.secretKey,
+            message: Buffer.from(_request.seed),
+          })
+        ],
+      });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Missing or invalid oracle signature"))
+    }
+    // Oracle signs the request seed
+    const tx = await program.rpc.executeBurn({
+      accounts: executeAccounts,
+      instructions: [
+        Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: oracleAuth.secretKey,
+          message: Buffer.from(_request.seed),
+        })
+      ],
+    });
+    // pTokens burnt
+    let _balance = await provider.connection.getTokenAccountBalance(quoteUserBurn)
+    assert.ok(_balance.value.amount == '0')
+    // Burn request settled
+    _request = await program.account.burnRequest.fetch(quoteBurnRequest)
+    assert.ok(_request.status.settled !== undefined)
+    // Receipt has a draw per ticket up to the first win, ticket i out of minted - i
+    let _pool = await program.account.probPool.fetch(quotePool.publicKey)
+    assert.ok(_request.draws.length >= 1 && _request.draws.length <= oracleBurnAmount.toNumber())
+    _request.draws.forEach((draw, i) => {
+      assert.ok(draw.lt(_pool.initialPtokenSupply.sub(new anchor.BN(i))))
+      assert.ok(draw.isZero() == (_request.won && i == _request.draws.length - 1))
+    })
+    assert.ok(_request.won == (_pool.prizes[0].status.won !== undefined))
+    if (!_request.won) {
+      assert.ok(_request.draws.length == oracleBurnAmount.toNumber())
+    }
+    // Back to slot hashes
+    await program.rpc.setOracle(
+      PublicKey.default,
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+        },
+        signers: [fortuneAuth]
+      });
+  });
+
+  it('Withdraw fees', async () => {
+    // Vault reconciles with quote mint accounting
+    let _quote = await program.account.quote.fetch(nativeQuote)
+    let _vaultBalance = await provider.connection.getTokenAccountBalance(fortuneVault)
+    assert.ok(new anchor.BN(_vaultBalance.value.amount).gt(new anchor.BN(0)))
+    assert.ok(_quote.feesCollected.sub(_quote.feesWithdrawn).toString() == _vaultBalance.value.amount)
+    const half = new anchor.BN(_vaultBalance.value.amount).div(new anchor.BN(2))
+    const feeRecipient = await Token.createWrappedNativeAccount(
+      provider.connection,
+      TOKEN_PROGRAM_ID,
+      fortuneAuth.publicKey,
+      fortuneAuth,
+      0
+    );
+    // Only the authority can withdraw
+    try {
+      await program.rpc.withdrawFees(
+        half,
+        {
+          accounts: {
+            authority: creatorAuth.publicKey,
+            recipient: feeRecipient,
+            fortuneLamportVault: fortuneVault,
+            quoteMint: NATIVE_MINT,
+            state: state,
+            quote: nativeQuote,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          },
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch {
+      assert.ok(true)
+    }
+    const tx = await program.rpc.withdrawFees(
+      half,
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          recipient: feeRecipient,
+          fortuneLamportVault: fortuneVault,
+          quoteMint: NATIVE_MINT,
+          quote: nativeQuote,
+          state: state,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [fortuneAuth]
+      });
+    let _balance = await provider.connection.getTokenAccountBalance(feeRecipient)
+    assert.ok(_balance.value.amount == half.toString())
+    let _vaultAfter = await provider.connection.getTokenAccountBalance(fortuneVault)
+    assert.ok(new anchor.BN(_vaultBalance.value.amount).sub(half).toString() == _vaultAfter.value.amount)
+    _quote = await program.account.quote.fetch(nativeQuote)
+    assert.ok(_quote.feesWithdrawn.eq(half))
+  });
+
+  it('Unwrap fees', async () => {
+    const solRecipient = Keypair.generate();
+    let _vaultBalance = await provider.connection.getTokenAccountBalance(fortuneVault)
+    const remaining = new anchor.BN(_vaultBalance.value.amount)
+    const tx = await program.rpc.unwrapFees(
+      remaining,
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          recipient: solRecipient.publicKey,
+          unwrapVault: unwrapVault,
+          fortuneLamportVault: fortuneVault,
+          nativeMint: NATIVE_MINT,
+          state: state,
+          quote: nativeQuote,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        },
+        signers: [fortuneAuth]
+      });
+    // Recipient got plain SOL
+    let _lamports = await provider.connection.getBalance(solRecipient.publicKey)
+    assert.ok(_lamports == remaining.toNumber())
+    // Vault emptied and fully accounted for
+    _vaultBalance = await provider.connection.getTokenAccountBalance(fortuneVault)
+    assert.ok(_vaultBalance.value.amount == '0')
+    let _quote = await program.account.quote.fetch(nativeQuote)
+    assert.ok(_quote.feesCollected.eq(_quote.feesWithdrawn))
+    // Temporary vault closed
+    assert.ok(await provider.connection.getAccountInfo(unwrapVault) == null)
+  });
+
+  it('Collection gated pools', async () => {
+    // Mint of the fixture metadata, its keypair is checked in so the metadata PDA is known
+    const collectionMint = Keypair.fromSecretKey(
+      Uint8Array.from(JSON.parse(fs.readFileSync("tests/fixtures/collection-nft-mint.json", "utf8")))
+    );
+    await sendAndConfirmTransaction(
+      provider.connection,
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: mintAuth.publicKey,
+          newAccountPubkey: collectionMint.publicKey,
+          lamports: await Token.getMinBalanceRentForExemptMint(provider.connection),
+          space: MintLayout.span,
+          programId: TOKEN_PROGRAM_ID,
+        }),
+        Token.createInitMintInstruction(TOKEN_PROGRAM_ID, collectionMint.publicKey, 0, mintAuth.publicKey, null)
+      ),
+      [mintAuth, collectionMint]
+    );
+    const collectionNft = new Token(provider.connection, collectionMint.publicKey, TOKEN_PROGRAM_ID, mintAuth);
+    const creatorCollectionNft = await collectionNft.createAccount(creatorAuth.publicKey);
+    await collectionNft.mintTo(creatorCollectionNft, mintAuth.publicKey, [mintAuth], 1);
+    // Same kind of NFT without metadata
+    const spamMint = await Token.createMint(
+      provider.connection,
+      mintAuth,
+      mintAuth.publicKey,
+      null,
+      0,
+      TOKEN_PROGRAM_ID
+    );
+    const creatorSpamNft = await spamMint.createAccount(creatorAuth.publicKey);
+    await spamMint.mintTo(creatorSpamNft, mintAuth.publicKey, [mintAuth], 1);
+    const collectionPrize = { prizeAccount: creatorCollectionNft, prizeMint: collectionMint.publicKey, quoteLamports: 0 }
+    const spamPrize = { prizeAccount: creatorSpamNft, prizeMint: spamMint.publicKey, quoteLamports: 0 }
+    // Only the fortune authority sets the allowlist
+    try {
+      await program.rpc.setCollections(
+        true,
+        [VERIFIED_COLLECTION],
+        {
+          accounts: {
+            authority: creatorAuth.publicKey,
+            state: state,
+            collections: collections,
+          },
+          signers: [creatorAuth]
+        });
+      assert.ok(false)
+    }
+    catch {
+      let _collections = await program.account.collections.fetch(collections)
+      assert.ok(!_collections.gated)
+    }
+    await program.rpc.setCollections(
+      true,
+      [VERIFIED_COLLECTION],
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+          collections: collections,
+        },
+        signers: [fortuneAuth]
+      });
+    let _collections = await program.account.collections.fetch(collections)
+    assert.ok(_collections.gated)
+    assert.ok(_collections.keys[0].equals(VERIFIED_COLLECTION))
+    // NFT without metadata is rejected
+    try {
+      await createTestPool({}, spamPrize)
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Prize is not in an allowed verified collection"))
+    }
+    // Declaring the NFT as a token prize does not get around the gate
+    try {
+      await createTestPool({ prize: { kind: { token: {} }, amount: one, weight: one } }, spamPrize)
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Prize is not in an allowed verified collection"))
+    }
+    // Metadata must be the PDA of the prize mint
+    try {
+      await createTestPool({}, {
+        ...spamPrize,
+        accounts: { nftMetadata: await metadataAddress(collectionMint.publicKey) },
+      })
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Metadata account does not match the mint"))
+    }
+    // Verified collection that is not on the allowlist is rejected
+    await program.rpc.setCollections(
+      true,
+      [Keypair.generate().publicKey],
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+          collections: collections,
+        },
+        signers: [fortuneAuth]
+      });
+    try {
+      await createTestPool({}, collectionPrize)
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Prize is not in an allowed verified collection"))
+    }
+    await program.rpc.setCollections(
+      true,
+      [Keypair.generate().publicKey, VERIFIED_COLLECTION],
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+          collections: collections,
+        },
+        signers: [fortuneAuth]
+      });
+    const { pool: gatedPool } = await createTestPool({}, collectionPrize)
+    let _pool = await program.account.probPool.fetch(gatedPool.publicKey)
+    assert.ok(_pool.prizes[0].mint.equals(collectionMint.publicKey))
+    // Reopen the gate
+    await program.rpc.setCollections(
+      false,
+      [],
+      {
+        accounts: {
+          authority: fortuneAuth.publicKey,
+          state: state,
+          collections: collections,
+        },
+        signers: [fortuneAuth]
+      });
+    _collections = await program.account.collections.fetch(collections)
+    assert.ok(!_collections.gated)
+    assert.ok(_collections.keys.length == 0)
+  });
+
+  it('Pool on a linear curve', async () => {
+    const linearSplAccount = Keypair.generate();
+    const {
+      pool: linearPool,
+      buyerQuote: linearQuote,
+      ptokenMint: linearPtokenMint,
+      splVault: linearSplVault,
+      ptokenVault: linearPtokenVault,
+      userVault: linearUserVault,
+    } = await createTestPool({ curve: { linear: {} } })
+    let _pool = await program.account.probPool.fetch(linearPool.publicKey)
+    assert.ok(_pool.curve.linear !== undefined)
+    assert.ok(_pool.initialLamportSupply.eq(splAmount))
+    // Area under l / n * (1 + 4t / n) from sold to sold + amount
+    const linearCost = (sold: anchor.BN, amount: anchor.BN) => {
+      const n = ptokenAmount
+      const area = amount.mul(n).muln(2).add(sold.muln(2).add(amount).mul(amount).muln(4))
+      return { numerator: splAmount.mul(area), denominator: n.mul(n).muln(2) }
+    }
+    const linearAccounts = {
+      poolLamportVault: linearSplVault,
+      poolPtokenVault: linearPtokenVault,
+      probPool: linearPool.publicKey,
+      fortuneLamportVault: fortuneVault,
+      creatorVault: await creatorVaultAddress(NATIVE_MINT, linearPool.publicKey),
+      lpVault: await lpVaultAddress(linearPool.publicKey),
+      userStats: await userStatsAddress(linearPool.publicKey, buyerAuth.publicKey),
+      userPtokenVault: linearUserVault,
+      ptokenMint: linearPtokenMint,
+      quoteMint: NATIVE_MINT,
+      state: state,
+      quote: nativeQuote,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY
+    };
+    // Buys round up
+    const bought = new anchor.BN(2)
+    const buyArea = linearCost(new anchor.BN(0), bought)
+    const cost = buyArea.numerator.add(buyArea.denominator).sub(one).div(buyArea.denominator)
+    const fee = cost.mul(swapFee).div(feeScalar)
+    await program.rpc.buy(
+      bought,
+      cost.add(fee),
+      bought,
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          userQuoteAccount: linearQuote,
+          ...linearAccounts
+        },
+        signers: [buyerAuth]
+      });
+    _pool = await program.account.probPool.fetch(linearPool.publicKey)
+    assert.ok(_pool.lamportSupply.eq(splAmount.add(cost)))
+    assert.ok(_pool.ptokenSupply.eq(ptokenAmount.sub(bought)))
+    let _splBalance = await provider.connection.getTokenAccountBalance(linearSplVault)
+    assert.ok(_splBalance.value.amount == cost.toString())
+    // Sells round down along the same line
+    const sellArea = linearCost(one, one)
+    const proceeds = sellArea.numerator.div(sellArea.denominator)
+    const sellFee = proceeds.mul(swapFee).div(feeScalar)
+    await program.rpc.sell(
+      one,
+      {
+        accounts: {
+          signer: buyerAuth.publicKey,
+          recipient: linearSplAccount.publicKey,
+          ...linearAccounts
+        },
+        signers: [buyerAuth, linearSplAccount]
+      });
+    _splBalance = await provider.connection.getTokenAccountBalance(linearSplAccount.publicKey)
+    assert.ok(_splBalance.value.amount == proceeds.sub(sellFee).toString())
+    _pool = await program.account.probPool.fetch(linearPool.publicKey)
+    assert.ok(_pool.lamportSupply.eq(splAmount.add(cost).sub(proceeds)))
+    // Liquidity deposits would move the curve
+    const linearLpMint = new Token(provider.connection, await lpMintAddress(linearPool.publicKey), TOKEN_PROGRAM_ID, mintAuth);
+    try {
+      await program.rpc.addLiquidity(
+        splAmount,
+        one,
+        {
+          accounts: {
+            signer: buyerAuth.publicKey,
+            userQuoteAccount: linearQuote,
+            lpAccount: await linearLpMint.createAccount(buyerAuth.publicKey),
+            poolLamportVault: linearSplVault,
+            poolPtokenVault: linearPtokenVault,
+            lpVault: await lpVaultAddress(linearPool.publicKey),
+            probPool: linearPool.publicKey,
+            ptokenMint: linearPtokenMint,
+            lpMint: linearLpMint.publicKey,
+            quoteMint: NATIVE_MINT,
+            tokenProgram: TOKEN_PROGRAM_ID
+          },
+          signers: [buyerAuth]
+        });
+      assert.ok(false)
+    }
+    catch (err) {
+      assert.ok(err.toString().includes("Not available for this pool's curve"))
+    }
   });
 });